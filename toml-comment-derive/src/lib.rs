@@ -1,6 +1,8 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
+use quote::{quote, quote_spanned};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
 use syn::{Data, DeriveInput, Fields, PathArguments, Type};
 
 const LEAF_TYPES: &[&str] = &[
@@ -8,16 +10,83 @@ const LEAF_TYPES: &[&str] = &[
     "usize", "isize", "String",
 ];
 
-fn emit_docs(docs: &[String]) -> Vec<TokenStream2> {
-    docs.iter()
-        .map(|doc| {
-            if doc.is_empty() {
-                quote! { out.push_str("#\n"); }
-            } else {
-                quote! { out.push_str(&format!("#{}\n", #doc)); }
-            }
-        })
-        .collect()
+fn emit_docs(docs: &FieldDocs, i18n_key: Option<&str>, vars: TokenStream2) -> TokenStream2 {
+    if docs.is_empty() && i18n_key.is_none() {
+        return TokenStream2::new();
+    }
+    let key = match i18n_key {
+        Some(key) => quote! { Some(#key) },
+        None => quote! { None },
+    };
+    let docs = docs.tokens();
+    quote! { toml_comment::push_docs(out, opts, #key, #docs, #vars); }
+}
+
+/// Like [`emit_docs`], for a struct's or enum's own docs, which render with
+/// the type-level comment prefix.
+fn emit_type_docs(docs: Vec<String>, i18n_key: Option<&str>) -> TokenStream2 {
+    if docs.is_empty() && i18n_key.is_none() {
+        return TokenStream2::new();
+    }
+    let key = match i18n_key {
+        Some(key) => quote! { Some(#key) },
+        None => quote! { None },
+    };
+    quote! { toml_comment::push_type_docs(out, opts, #key, &[#(#docs),*]); }
+}
+
+/// Doc lines for a field: its own `///` comments, or, with
+/// `#[toml_comment(type_docs)]` and no comments of its own, the docs its
+/// type exposes through `TypeDocs`.
+enum FieldDocs {
+    Own(Vec<String>),
+    Type(Type),
+}
+
+impl FieldDocs {
+    fn is_empty(&self) -> bool {
+        matches!(self, Self::Own(docs) if docs.is_empty())
+    }
+
+    fn uses(&self, placeholder: &str) -> bool {
+        match self {
+            Self::Own(docs) => docs.iter().any(|doc| doc.contains(placeholder)),
+            Self::Type(_) => true,
+        }
+    }
+
+    fn tokens(&self) -> TokenStream2 {
+        match self {
+            Self::Own(docs) => quote! { &[#(#docs),*] },
+            Self::Type(ty) => quote! { <#ty as toml_comment::TypeDocs>::DOCS },
+        }
+    }
+}
+
+/// Placeholder values for a field's comment, limited to the ones its docs
+/// actually use (all of them when a localizer may supply the text).
+fn doc_vars(
+    docs: &FieldDocs,
+    attrs: &FieldAttrs,
+    default_value: TokenStream2,
+    ty_name: &str,
+    is_section: bool,
+) -> TokenStream2 {
+    let uses = |name: &str| attrs.i18n_key.is_some() || docs.uses(&format!("{{{name}}}"));
+    let mut vars = Vec::new();
+    if uses("type") {
+        vars.push(quote! { ("type", #ty_name) });
+    }
+    if uses("env") {
+        let env = attrs.env.as_deref().unwrap_or_default();
+        vars.push(quote! { ("env", #env) });
+    }
+    if uses("default") && !is_section {
+        vars.push(quote! {
+            ("default", #default_value.map_or_else(|| "unset".to_string(), |val| toml_comment::fmt_value(&val)).as_str())
+        });
+    }
+    quote! { &[#(#vars),*] }
 }
 
 #[proc_macro_derive(TomlComment, attributes(toml_comment))]
@@ -25,103 +94,1643 @@ pub fn derive_toml_comment(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
 
-    let Data::Struct(data) = &input.data else {
-        return TokenStream::new();
+    let data = match &input.data {
+        Data::Struct(data) => data,
+        Data::Enum(data) => {
+            return match derive_enum(&input, data) {
+                Ok(tokens) => tokens.into(),
+                Err(err) => err.to_compile_error().into(),
+            };
+        }
+        Data::Union(_) => return TokenStream::new(),
     };
     let Fields::Named(named) = &data.fields else {
         panic!("TomlComment only supports structs with named fields");
     };
 
     let struct_docs = extract_docs(&input.attrs);
+    let container = match ContainerAttrs::parse(&input.attrs) {
+        Ok(attrs) => attrs,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let container_serde = match SerdeAttrs::parse(&input.attrs) {
+        Ok(attrs) => attrs,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let has_struct_docs = !struct_docs.is_empty() || container.i18n_key.is_some();
     let mut render_body: Vec<TokenStream2> = Vec::new();
+    let mut fields_body: Vec<TokenStream2> = Vec::new();
 
-    let struct_doc_tokens = emit_docs(&struct_docs);
-    if !struct_doc_tokens.is_empty() {
-        render_body.extend(struct_doc_tokens);
-    }
+    let struct_doc_tokens = emit_type_docs(struct_docs.clone(), container.i18n_key.as_deref());
 
     let mut first_section = true;
+    let mut missing_docs: Option<syn::Error> = None;
+    // Trait bounds checked against each field's own type, so a missing
+    // impl is reported on the field rather than inside generated code.
+    let mut bound_checks: Vec<TokenStream2> = Vec::new();
+    // `_render_section` arms: a table field renders as in `_render` when
+    // the path names it, and passes the rest of the path down otherwise.
+    let mut section_arms: Vec<TokenStream2> = Vec::new();
+    // `_size_hint` terms: the docs and key lines known here, plus what
+    // nested tables and collections hold at runtime.
+    let mut size_body: Vec<TokenStream2> = Vec::new();
+    // Plain `key = value` fields become rows of one `Leaf` table rendered
+    // by `push_leaf`; a run of them in a row renders in one loop.
+    let mut leaves: Vec<TokenStream2> = Vec::new();
+    let mut leaf_run: Option<(usize, usize)> = None;
+    // Keys that belong to the struct's own table wherever their field sits,
+    // such as an empty `Option<Vec<Struct>>`'s `key = []`, go before the
+    // first field that opens a table.
+    let mut first_table_at: Option<usize> = None;
+    let mut early_body: Vec<TokenStream2> = Vec::new();
 
     for field in &named.named {
+        // Type markers and `()` hold no data; serde writes them as unit,
+        // which TOML can't represent.
+        if is_phantom_data(&field.ty) || is_unit_type(&field.ty) {
+            continue;
+        }
         let field_name = field.ident.as_ref().expect("named field");
-        let field_name_str = field_name.to_string();
-        let field_docs = extract_docs(&field.attrs);
-        let force_inline = has_toml_comment_attr(&field.attrs, "inline");
+        let attrs = match FieldAttrs::parse(&field.attrs) {
+            Ok(attrs) => attrs,
+            Err(err) => return err.to_compile_error().into(),
+        };
+        let serde = match SerdeAttrs::parse(&field.attrs) {
+            Ok(attrs) => attrs,
+            Err(err) => return err.to_compile_error().into(),
+        };
+        let doc_hidden = is_doc_hidden(&field.attrs);
+        let cfgs: Vec<_> = field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("cfg"))
+            .collect();
+        let fields_start = fields_body.len();
+        let own_docs = if doc_hidden {
+            Vec::new()
+        } else {
+            extract_docs(&field.attrs)
+        };
+        if container.deny_missing_docs
+            && own_docs.is_empty()
+            && !doc_hidden
+            && !attrs.flatten
+            && !attrs.type_docs
+            && attrs.i18n_key.is_none()
+            && attrs.doc_fn.is_none()
+        {
+            let err = syn::Error::new_spanned(
+                field_name,
+                format!("`{field_name}` has no doc comment (`deny_missing_docs`)"),
+            );
+            match &mut missing_docs {
+                Some(errors) => errors.combine(err),
+                None => missing_docs = Some(err),
+            }
+        }
+        let field_docs = if own_docs.is_empty() && attrs.type_docs {
+            FieldDocs::Type(option_inner(&field.ty).unwrap_or(&field.ty).clone())
+        } else {
+            FieldDocs::Own(own_docs)
+        };
+        let docs_len: usize = match &field_docs {
+            FieldDocs::Own(docs) => docs.iter().map(|doc| doc.len() + 3).sum(),
+            FieldDocs::Type(_) => 0,
+        };
+        // A `serde(with)` function decides the value's shape, so the field
+        // is a plain value whatever its Rust type (`Duration`, ...). Enums
+        // listing their `choices` are plain values too.
+        let force_inline =
+            attrs.inline || attrs.untagged || attrs.choices || serde.serialize_with.is_some();
+        // `Option<Vec<Struct>>` and `Option<Map>` keep their collection
+        // layout when `Some` and leave a commented header when `None`.
+        let optional_collection = option_inner(&field.ty)
+            .filter(|inner| !force_inline && (is_map_type(inner) || is_table_array_type(inner)));
+        let collection_ty = optional_collection.unwrap_or(&field.ty);
+        let is_optional = optional_collection.is_some();
+        let is_map = !force_inline && is_map_type(collection_ty);
+        // `Option<Box<T>>` is how recursive configs end; it renders as a
+        // section when `Some` and is left out when `None`.
+        let optional_section = !force_inline
+            && option_inner(&field.ty)
+                .and_then(box_inner)
+                .is_some_and(is_section_type);
+        // `serde_json::Value` and `toml::Value` can hold anything; what
+        // they hold decides whether they render as a key or a table.
+        let is_dynamic = !force_inline && is_dynamic_type(&field.ty);
+        let is_section = !force_inline
+            && !is_map
+            && !is_dynamic
+            && (is_section_type(box_inner(&field.ty).unwrap_or(&field.ty)) || optional_section);
+        let is_tables = !force_inline && is_table_array_type(collection_ty);
+        let ty = &field.ty;
+        if serde.serialize_with.is_none() {
+            bound_checks.push(quote_spanned! {ty.span()=>
+                #(#cfgs)*
+                assert_serialize::<#ty>();
+            });
+        }
+        let nested = if is_section {
+            let ty = option_inner(ty).unwrap_or(ty);
+            Some(box_inner(ty).unwrap_or(ty))
+        } else if is_tables {
+            seq_inner(collection_ty)
+        } else {
+            None
+        };
+        if let Some(nested) = nested {
+            bound_checks.push(quote_spanned! {nested.span()=>
+                #(#cfgs)*
+                assert_toml_comment::<#nested>();
+            });
+        }
+        // The key serde reads and writes: its own `rename`, else the
+        // container's `rename_sections` for tables, else `rename_all`.
+        let ident_str = field_name.unraw().to_string();
+        let field_name_str = match (&serde.rename, &container.rename_sections) {
+            (Some(rename), _) => rename.clone(),
+            (None, Some(rule)) if is_section || is_tables => rule.apply(&ident_str),
+            _ => match &container_serde.rename_all {
+                Some(rule) => rule.apply(&ident_str),
+                None => ident_str,
+            },
+        };
+
+        if attrs.section_docs != SectionDocs::Inside && !is_section && !is_tables {
+            return syn::Error::new_spanned(
+                field_name,
+                "`section_docs` is only supported on section fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if attrs.type_docs && (is_section || is_tables) {
+            return syn::Error::new_spanned(
+                field_name,
+                "section types already render their own docs, see `section_docs`",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if attrs.flatten && (!is_section || optional_section) {
+            return syn::Error::new_spanned(
+                field_name,
+                "`flatten` is only supported on section fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if attrs.prefix.is_some() && !attrs.flatten {
+            return syn::Error::new_spanned(
+                field_name,
+                "`prefix` is only supported with `flatten`",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if attrs.map_tables && !is_map {
+            return syn::Error::new_spanned(
+                field_name,
+                "`map_tables` is only supported on map fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if attrs.key_docs.is_some() && !is_map {
+            return syn::Error::new_spanned(
+                field_name,
+                "`key_docs` is only supported on map fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if attrs.env.is_some() && (is_map || is_section || is_tables) {
+            return syn::Error::new_spanned(field_name, "`env` is only supported on leaf fields")
+                .to_compile_error()
+                .into();
+        }
+        if attrs.number_format().is_some() && (is_map || is_section || is_tables) {
+            return syn::Error::new_spanned(
+                field_name,
+                "number formats are only supported on leaf fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if attrs.unit.is_some() && (is_map || is_section || is_tables) {
+            return syn::Error::new_spanned(field_name, "`unit` is only supported on leaf fields")
+                .to_compile_error()
+                .into();
+        }
+        if attrs.placeholder.is_some()
+            && (!is_option_type(&field.ty) || is_map || is_section || is_tables)
+        {
+            return syn::Error::new_spanned(
+                field_name,
+                "`placeholder` is only supported on `Option` leaf fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if attrs.elements.is_some()
+            && !matches!(&field.ty, Type::Tuple(_))
+            && !is_seq_type(option_inner(&field.ty).unwrap_or(&field.ty))
+        {
+            return syn::Error::new_spanned(
+                field_name,
+                "`elements` is only supported on tuple and array fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if (attrs.secret || attrs.choices || attrs.min.is_some() || attrs.max.is_some())
+            && (is_map || is_section || is_tables)
+        {
+            return syn::Error::new_spanned(
+                field_name,
+                "`secret`, `choices`, `min` and `max` are only supported on leaf fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+        let env = match &attrs.env {
+            Some(env) => quote! { Some(#env) },
+            None => quote! { None },
+        };
+        // Fixed annotations rendered after the docs.
+        let mut notes = Vec::new();
+        if let Some(unit) = &attrs.unit {
+            notes.push(format!("unit: {unit}"));
+        }
+        if let Some(elements) = &attrs.elements {
+            notes.push(format!("elements: {elements}"));
+        }
+        if !serde.aliases.is_empty() {
+            notes.push(format!("also accepted: {}", serde.aliases.join(", ")));
+        }
+        if serde.default {
+            notes.push("optional — may be omitted".to_string());
+        }
+        notes.extend(attrs.requires.iter().map(|key| format!("requires: {key}")));
+        notes.extend(
+            attrs
+                .conflicts_with
+                .iter()
+                .map(|key| format!("conflicts with: {key}")),
+        );
+        notes.extend(attrs.see.iter().map(|see| format!("see: {see}")));
+        let aliases = &serde.aliases;
+        let (requires, conflicts_with) = (&attrs.requires, &attrs.conflicts_with);
+        let example_value = match &attrs.example {
+            Some(example) => {
+                quote! { Some(format!("e.g. {}", toml_comment::fmt_default(&(#example)))) }
+            }
+            None => quote! { None },
+        };
+        let ty_name = type_name(&field.ty);
+        let kind = if is_dynamic {
+            quote! {
+                match &value {
+                    Some(toml::Value::Table(_)) => toml_comment::FieldKind::Map,
+                    _ => toml_comment::FieldKind::Value,
+                }
+            }
+        } else if is_map {
+            quote! { toml_comment::FieldKind::Map }
+        } else if is_section {
+            quote! { toml_comment::FieldKind::Section }
+        } else if is_tables {
+            quote! { toml_comment::FieldKind::Tables }
+        } else {
+            quote! { toml_comment::FieldKind::Value }
+        };
+        let sort_keys = is_hash_map_type(collection_ty);
+        let value = if is_section || is_tables {
+            quote! { None }
+        } else if is_optional {
+            quote! {
+                self.#field_name.as_ref().map(|map| {
+                    toml::Value::Table(toml_comment::map_table(map))
+                })
+            }
+        } else if is_map {
+            quote! {
+                Some(toml::Value::Table(toml_comment::map_table(&self.#field_name)))
+            }
+        } else {
+            let value = to_value(quote! { &self.#field_name }, &field.ty, &serde);
+            quote! { #value.ok() }
+        };
+        let docs_expr = field_docs.tokens();
+        let optional = is_option_type(&field.ty) || serde.default;
+        let unit = match &attrs.unit {
+            Some(unit) => quote! { Some(#unit) },
+            None => quote! { None },
+        };
+        let secret = attrs.secret;
+        let bound = |lit: &Option<syn::Lit>| match lit {
+            Some(lit) => quote! { Some(#lit as f64) },
+            None => quote! { None },
+        };
+        let (min, max) = (bound(&attrs.min), bound(&attrs.max));
+        let choices = if attrs.choices {
+            let ty = option_inner(&field.ty).unwrap_or(&field.ty);
+            let ty = seq_inner(ty).unwrap_or(ty);
+            quote! { <#ty as toml_comment::TypeDocs>::CHOICES }
+        } else {
+            quote! { &[] }
+        };
+        if attrs.flatten {
+            fields_body.push(quote! {
+                let start = out.len();
+                self.#field_name._fields(out, prefix);
+            });
+            if let Some(key_prefix) = &attrs.prefix {
+                fields_body.push(quote! {
+                    for field in &mut out[start..] {
+                        field.path = if prefix.is_empty() {
+                            format!("{}{}", #key_prefix, field.path)
+                        } else {
+                            format!("{}.{}{}", prefix, #key_prefix, &field.path[prefix.len() + 1..])
+                        };
+                    }
+                });
+            }
+        } else {
+            fields_body.push(quote! {
+                let path = if prefix.is_empty() {
+                    #field_name_str.to_string()
+                } else {
+                    format!("{}.{}", prefix, #field_name_str)
+                };
+                let value = #value;
+                out.push(toml_comment::FieldInfo {
+                    path: path.clone(),
+                    ty: #ty_name,
+                    kind: #kind,
+                    docs: #docs_expr,
+                    value,
+                    env: #env,
+                    optional: #optional,
+                    unit: #unit,
+                    secret: #secret,
+                    min: #min,
+                    max: #max,
+                    choices: #choices,
+                    example: #example_value,
+                    notes: &[#(#notes),*],
+                    aliases: &[#(#aliases),*],
+                    requires: &[#(#requires),*],
+                    conflicts_with: &[#(#conflicts_with),*],
+                });
+            });
+        }
+        // An enum holding a unit variant is a plain `key = "Variant"`.
+        let section_fields = |section: TokenStream2| {
+            quote! {
+                match #section._unit_variant() {
+                    Some((variant, _)) => {
+                        if let Some(field) = out.last_mut() {
+                            field.kind = toml_comment::FieldKind::Value;
+                            field.value = Some(toml::Value::String(variant.to_string()));
+                        }
+                    }
+                    None => #section._fields(out, &path),
+                }
+            }
+        };
+        if optional_section {
+            let fields = section_fields(quote! { section });
+            fields_body.push(quote! {
+                if let Some(section) = &self.#field_name {
+                    #fields
+                }
+            });
+        } else if is_section && !attrs.flatten {
+            fields_body.push(section_fields(quote! { self.#field_name }));
+        }
+        let items = if is_optional {
+            quote! { self.#field_name.iter().flatten() }
+        } else {
+            quote! { self.#field_name.iter() }
+        };
+        if is_tables {
+            fields_body.push(quote! {
+                for (i, item) in #items.enumerate() {
+                    item._fields(out, &format!("{}[{}]", path, i));
+                }
+            });
+        }
 
-        if !force_inline && is_map_type(&field.ty) {
-            let doc_tokens = emit_docs(&field_docs);
+        let unit_variant = if optional_section {
+            quote! { self.#field_name.as_ref().and_then(|value| value._unit_variant()) }
+        } else {
+            quote! { self.#field_name._unit_variant() }
+        };
+        let render_start = render_body.len();
+        let opens_table = is_tables
+            || is_dynamic
+            || (is_section && !attrs.flatten)
+            || (is_map && (is_optional || attrs.map_tables));
+        if opens_table && first_table_at.is_none() {
+            first_table_at = Some(render_start);
+        }
+        let flatten_opts = match &attrs.prefix {
+            Some(key_prefix) => quote! {
+                &toml_comment::RenderOptions {
+                    key_prefix: &format!("{}{}", opts.key_prefix, #key_prefix),
+                    ..*opts
+                }
+            },
+            None => quote! { opts },
+        };
+        // Keys of a `flatten(prefix = "...")` struct carry the prefix; the
+        // tables below them start over.
+        let key = quote! { toml_comment::prefixed_key(opts.key_prefix, #field_name_str) };
+        let nested_opts =
+            quote! { &toml_comment::RenderOptions { key_prefix: "", in_table: true, ..*opts } };
+        let table_opts = quote! { &toml_comment::RenderOptions { in_table: true, ..*opts } };
+        let margin_opts = quote! { &toml_comment::RenderOptions { in_table: false, ..*opts } };
+        let field_value = to_value(quote! { &self.#field_name }, &field.ty, &serde);
+        let default_field = to_value(quote! { &default.#field_name }, &field.ty, &serde);
+        let default_value = quote! {
+            {
+                #[allow(unused_imports)]
+                use toml_comment::{NoDefault as _, ViaDefault as _};
+                (&&toml_comment::DefaultOf::<Self>::new())
+                    .value()
+                    .and_then(|default| #default_field.ok())
+            }
+        };
+        let vars = doc_vars(
+            &field_docs,
+            &attrs,
+            default_value.clone(),
+            &ty_name,
+            is_section,
+        );
+        let banner = quote! { toml_comment::push_banner(out, opts, prefix, #field_name_str); };
+        let number_format = attrs
+            .number_format()
+            .unwrap_or_else(|| quote! { toml_comment::NumberFormat::default() });
+        let mut doc_tokens = emit_docs(&field_docs, attrs.i18n_key.as_deref(), vars);
+        if let Some(doc_fn) = &attrs.doc_fn {
+            let call = match doc_fn.get_ident() {
+                Some(method) => quote! { self.#method() },
+                None => quote! { #doc_fn(self) },
+            };
+            doc_tokens.extend(quote! {
+                {
+                    let lines: Vec<String> = #call;
+                    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+                    toml_comment::push_docs(out, opts, None, &lines, &[]);
+                }
+            });
+        }
+        if let Some(example) = &attrs.example {
+            doc_tokens.extend(quote! {
+                {
+                    let example = format!("e.g. {}", toml_comment::fmt_default(&(#example)));
+                    toml_comment::push_docs(out, opts, None, &[example.as_str()], &[]);
+                }
+            });
+        }
+        if !notes.is_empty() {
+            doc_tokens.extend(quote! {
+                toml_comment::push_docs(out, opts, None, &[#(#notes),*], &[]);
+            });
+        }
+        if is_option_type(&field.ty) && !serde.default {
+            doc_tokens.extend(quote! {
+                if opts.mark_optional {
+                    toml_comment::push_docs(out, opts, None, &["optional"], &[]);
+                }
+            });
+        }
+        // A `serde(with)` function can write any shape, so its hint comes
+        // from the value it wrote rather than from the Rust type.
+        let type_hint = if is_map || is_section || is_tables || serde.serialize_with.is_some() {
+            None
+        } else {
+            toml_type_name(&field.ty).map(|toml_type| format!("type: {toml_type}"))
+        };
+        if let Some(hint) = &type_hint {
+            doc_tokens.extend(quote! {
+                if opts.type_hints {
+                    toml_comment::push_docs(out, opts, None, &[#hint], &[]);
+                }
+            });
+        } else if serde.serialize_with.is_some() {
+            doc_tokens.extend(quote! {
+                if opts.type_hints
+                    && let Ok(val) = #field_value
+                    && let Some(toml_type) = toml_comment::toml_type_of(&val)
+                {
+                    let hint = format!("type: {toml_type}");
+                    toml_comment::push_docs(out, opts, None, &[hint.as_str()], &[]);
+                }
+            });
+        }
+        // `annotate` lines after the default, which is only known at runtime.
+        let mut annotation_lines = Vec::new();
+        if let Some(env) = &attrs.env {
+            annotation_lines.push(format!("env: {env}"));
+        }
+        let bound = |lit: &Option<syn::Lit>| match lit {
+            Some(syn::Lit::Int(lit)) => Some(lit.base10_digits().to_string()),
+            Some(syn::Lit::Float(lit)) => Some(lit.base10_digits().to_string()),
+            _ => None,
+        };
+        match (bound(&attrs.min), bound(&attrs.max)) {
+            (Some(min), Some(max)) => annotation_lines.push(format!("range: {min} to {max}")),
+            (Some(min), None) => annotation_lines.push(format!("minimum: {min}")),
+            (None, Some(max)) => annotation_lines.push(format!("maximum: {max}")),
+            (None, None) => {}
+        }
+        if !is_map && !is_section && !is_tables {
+            doc_tokens.extend(quote! {
+                if opts.annotate {
+                    let mut lines: Vec<String> = Vec::new();
+                    if let Some(val) = #default_value {
+                        lines.push(format!("default: {}", toml_comment::fmt_value(&val)));
+                    }
+                    #(lines.push(#annotation_lines.to_string());)*
+                    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+                    toml_comment::push_docs(out, opts, None, &lines, &[]);
+                }
+            });
+        }
+        if attrs.choices {
+            doc_tokens.extend(quote! {
+                if opts.list_choices {
+                    let choices: Vec<String> =
+                        #choices.iter().map(|c| toml_comment::fmt_default(c)).collect();
+                    let line = format!("one of: {}", choices.join(", "));
+                    toml_comment::push_docs(out, opts, None, &[line.as_str()], &[]);
+                }
+            });
+        }
+        if attrs.untagged {
+            let ty = option_inner(&field.ty).unwrap_or(&field.ty);
+            doc_tokens.extend(quote! {
+                toml_comment::push_forms(out, opts, <#ty as toml_comment::TypeDocs>::FORMS);
+            });
+        }
+        // A table's own comments go above its header, at the margin.
+        let header_docs = quote! {
+            {
+                let opts = #margin_opts;
+                #doc_tokens
+            }
+        };
+        if is_map && is_optional {
+            let blank = if !first_section || has_struct_docs {
+                quote! { toml_comment::push_gap(out, opts); }
+            } else {
+                TokenStream2::new()
+            };
+            first_section = false;
+            let entries_body = if attrs.map_tables {
+                let key_docs = match &attrs.key_docs {
+                    Some(key_docs) => quote! { Some(&|k: &str| #key_docs(k)) },
+                    None => quote! { None },
+                };
+                quote! {
+                    let entries = toml_comment::map_entries(map, #sort_keys, opts);
+                    if entries.is_empty() {
+                        toml_comment::push_header(out, opts, &section, false);
+                    } else {
+                        toml_comment::push_map_tables(out, opts, &section, entries.iter().map(|(k, v)| (k, v)), #key_docs);
+                    }
+                }
+            } else {
+                let key_docs = match &attrs.key_docs {
+                    Some(key_docs) => quote! {
+                        if let Some(doc) = #key_docs(k) {
+                            toml_comment::push_docs(out, opts, None, &[doc.as_str()], &[]);
+                        }
+                    },
+                    None => TokenStream2::new(),
+                };
+                quote! {
+                    toml_comment::push_header(out, opts, &section, false);
+                    let opts = #table_opts;
+                    toml_comment::for_each_map_entry(map, #sort_keys, opts, |k, v| {
+                        let k = k.as_str();
+                        #key_docs
+                        toml_comment::push_entry(out, k, &v, &toml_comment::NumberFormat::default(), opts);
+                    });
+                }
+            };
             render_body.push(quote! {
-                let map_val = toml::Value::try_from(&self.#field_name).unwrap();
-                if let toml::Value::Table(table) = map_val {
-                    if !table.is_empty() {
-                        #(#doc_tokens)*
-                        for (k, v) in &table {
-                            out.push_str(&format!("{} = {}\n", k, toml_comment::fmt_value(v)));
+                let key = toml_comment::fmt_key(&#key);
+                let section = prefix.join(&key).to_string();
+                #blank
+                #banner
+                #header_docs
+                match &self.#field_name {
+                    Some(map) => {
+                        #entries_body
+                    }
+                    None => { toml_comment::push_absent_header(out, opts, &section, false); }
+                }
+            });
+        } else if is_dynamic {
+            let blank = if !first_section || has_struct_docs {
+                quote! { toml_comment::push_gap(out, opts); }
+            } else {
+                TokenStream2::new()
+            };
+            first_section = false;
+            render_body.push(quote! {
+                match #field_value {
+                    Ok(toml::Value::Table(table)) => {
+                        let key = toml_comment::fmt_key(&#key);
+                        let section = prefix.join(&key).to_string();
+                        #blank
+                        #banner
+                        #header_docs
+                        if table.is_empty() {
+                            toml_comment::push_header(out, opts, &section, false);
+                        } else {
+                            toml_comment::push_map_tables(out, opts, &section, &table, None);
                         }
                     }
+                    Ok(val) => {
+                        #doc_tokens
+                        toml_comment::push_entry(out, &#key, &val, &#number_format, opts);
+                    }
+                    // `null` has no TOML form.
+                    Err(_) => {}
                 }
             });
-        } else if !force_inline && is_section_type(&field.ty) {
-            let emit_blank = !first_section || !struct_docs.is_empty();
+        } else if is_map && attrs.map_tables {
+            let blank = if !first_section || has_struct_docs {
+                quote! { toml_comment::push_gap(out, opts); }
+            } else {
+                TokenStream2::new()
+            };
             first_section = false;
+            let key_docs = match &attrs.key_docs {
+                Some(key_docs) => quote! { Some(&|k: &str| #key_docs(k)) },
+                None => quote! { None },
+            };
+            render_body.push(quote! {
+                let entries = toml_comment::map_entries(&self.#field_name, #sort_keys, opts);
+                if !entries.is_empty() {
+                    let key = toml_comment::fmt_key(&#key);
+                    let section = prefix.join(&key).to_string();
+                    #blank
+                    #banner
+                    #header_docs
+                    toml_comment::push_map_tables(out, opts, &section, entries.iter().map(|(k, v)| (k, v)), #key_docs);
+                }
+            });
+        } else if is_map {
+            let key_docs = match &attrs.key_docs {
+                Some(key_docs) => quote! {
+                    if let Some(doc) = #key_docs(k) {
+                        toml_comment::push_docs(out, opts, None, &[doc.as_str()], &[]);
+                    }
+                },
+                None => TokenStream2::new(),
+            };
+            render_body.push(quote! {
+                // The docs go above the first entry that renders, if any.
+                let mut docs = true;
+                toml_comment::for_each_map_entry(&self.#field_name, #sort_keys, opts, |k, v| {
+                    if std::mem::take(&mut docs) {
+                        #doc_tokens
+                    }
+                    let k = k.as_str();
+                    #key_docs
+                    toml_comment::push_entry(out, k, &v, &toml_comment::NumberFormat::default(), opts);
+                });
+            });
+        } else if attrs.flatten {
+            render_body.push(doc_tokens);
+            render_body.push(quote! {
+                self.#field_name._render(out, prefix, #flatten_opts);
+            });
+        } else if is_section {
+            let emit_blank = !first_section || has_struct_docs;
+            first_section = false;
+            let target = if optional_section {
+                quote! { value }
+            } else {
+                quote! { self.#field_name }
+            };
 
             render_body.push(quote! {
-                let section = if prefix.is_empty() {
-                    #field_name_str.to_string()
-                } else {
-                    format!("{}.{}", prefix, #field_name_str)
-                };
+                let key = #key;
+                let section = prefix.join(&key);
+                if !toml_comment::check_depth(opts, section) {
+                    return Default::default();
+                }
             });
 
             if emit_blank {
-                render_body.push(quote! { out.push('\n'); });
+                render_body.push(quote! { toml_comment::push_gap(out, opts); });
             }
+            render_body.push(banner.clone());
 
-            let doc_tokens = emit_docs(&field_docs);
-            render_body.extend(doc_tokens);
+            match attrs.section_docs {
+                SectionDocs::Above => {
+                    render_body.push(quote! { #target._render_docs(out, #margin_opts); });
+                    render_body.push(header_docs);
+                    render_body
+                        .push(quote! { toml_comment::push_header(out, opts, &section, false); });
+                }
+                SectionDocs::Inside => {
+                    render_body.push(header_docs);
+                    render_body
+                        .push(quote! { toml_comment::push_header(out, opts, &section, false); });
+                    render_body.push(quote! { #target._render_docs(out, #table_opts); });
+                }
+                SectionDocs::Hidden => {
+                    render_body.push(header_docs);
+                    render_body
+                        .push(quote! { toml_comment::push_header(out, opts, &section, false); });
+                }
+            }
+            render_body.push(quote! {
+                #target._render(out, section, #nested_opts);
+            });
+            let section_render: Vec<_> = render_body.drain(render_start..).collect();
+            render_body.push(quote! {
+                if #target._unit_variant().is_none() {
+                    #(#section_render)*
+                }
+            });
+            let mut unit_entry = quote! {
+                if let Some((variant, docs)) = #unit_variant {
+                    #doc_tokens
+                    toml_comment::push_docs(out, opts, None, docs, &[]);
+                    let value = toml::Value::String(variant.to_string());
+                    toml_comment::push_entry(out, &#key, &value, &toml_comment::NumberFormat::DEFAULT, opts);
+                }
+            };
+            if doc_hidden {
+                unit_entry = quote! {
+                    if !opts.skip_hidden {
+                        #unit_entry
+                    }
+                };
+            }
+            early_body.push(quote! {
+                #(#cfgs)*
+                {
+                    #unit_entry
+                }
+            });
+            if optional_section {
+                let section_render: Vec<_> = render_body.drain(render_start..).collect();
+                render_body.push(quote! {
+                    if let Some(value) = &self.#field_name {
+                        #(#section_render)*
+                    }
+                });
+            }
+        } else if is_tables {
+            let blank = if !first_section || has_struct_docs {
+                quote! { toml_comment::push_gap(out, opts); }
+            } else {
+                TokenStream2::new()
+            };
+            first_section = false;
 
+            let (docs_once, docs_each) = match attrs.section_docs {
+                SectionDocs::Above => (
+                    quote! { item._render_docs(out, #margin_opts); },
+                    TokenStream2::new(),
+                ),
+                SectionDocs::Inside => (
+                    TokenStream2::new(),
+                    quote! { item._render_docs(out, #table_opts); },
+                ),
+                SectionDocs::Hidden => (TokenStream2::new(), TokenStream2::new()),
+            };
+            let section = quote! {
+                let key = #key;
+                let section = prefix.join(&key);
+            };
+            let tables = quote! {
+                #section
+                #blank
+                #banner
+                #header_docs
+                if let Some(item) = items.iter().next() {
+                    #docs_once
+                }
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        toml_comment::push_gap(out, opts);
+                    }
+                    if !toml_comment::check_depth(opts, section) {
+                        return Default::default();
+                    }
+                    toml_comment::push_header(out, opts, &section, true);
+                    #docs_each
+                    item._render(out, section, #nested_opts);
+                }
+            };
+            if is_optional {
+                let mut empty = quote! {
+                    if self.#field_name.as_ref().is_some_and(|items| items.is_empty()) {
+                        #doc_tokens
+                        toml_comment::push_entry(out, &#key, &toml::Value::Array(Vec::new()), &toml_comment::NumberFormat::DEFAULT, opts);
+                    }
+                };
+                if doc_hidden {
+                    empty = quote! {
+                        if !opts.skip_hidden {
+                            #empty
+                        }
+                    };
+                }
+                early_body.push(quote! {
+                    #(#cfgs)*
+                    {
+                        #empty
+                    }
+                });
+                render_body.push(quote! {
+                    match &self.#field_name {
+                        Some(items) if !items.is_empty() => {
+                            #tables
+                        }
+                        Some(_) => {}
+                        None => {
+                            #section
+                            #blank
+                            #banner
+                            #header_docs
+                            toml_comment::push_absent_header(out, opts, &section, true);
+                        }
+                    }
+                });
+            } else {
+                render_body.push(quote! {
+                    let items = &self.#field_name;
+                    if !items.is_empty() {
+                        #tables
+                    }
+                });
+            }
+        } else if option_inner(&field.ty).is_some_and(is_seq_type) {
+            let placeholder = attrs.placeholder.as_deref().unwrap_or("[]");
+            render_body.push(doc_tokens);
             render_body.push(quote! {
-                out.push_str(&format!("[{}]\n", section));
-                self.#field_name._render(out, &section);
+                match &self.#field_name {
+                    Some(items) => {
+                        let val = #field_value.unwrap();
+                        toml_comment::push_entry(out, &#key, &val, &#number_format, opts);
+                    }
+                    None => { toml_comment::push_unset(out, opts, &#key, #placeholder); }
+                }
             });
         } else if is_option_type(&field.ty) {
-            let doc_tokens = emit_docs(&field_docs);
+            let placeholder = match (
+                &attrs.placeholder,
+                option_inner(&field.ty).and_then(toml_type_name),
+            ) {
+                (Some(placeholder), _) => placeholder.clone(),
+                (None, Some(toml_type)) => format!("<{toml_type}>"),
+                (None, None) => "<value>".to_string(),
+            };
+            let unset = if attrs.placeholder.is_some() {
+                quote! { else }
+            } else {
+                quote! { else if opts.show_unset }
+            };
             render_body.push(quote! {
                 if self.#field_name.is_some() {
-                    #(#doc_tokens)*
-                    let val = toml::Value::try_from(&self.#field_name).unwrap();
-                    out.push_str(&format!("{} = {}\n", #field_name_str, toml_comment::fmt_value(&val)));
+                    #doc_tokens
+                    let val = #field_value.unwrap();
+                    toml_comment::push_entry(out, &#key, &val, &#number_format, opts);
+                } #unset {
+                    #doc_tokens
+                    { toml_comment::push_unset(out, opts, &#key, #placeholder); }
                 }
             });
+        } else if cfgs.is_empty()
+            && !doc_hidden
+            && !attrs.choices
+            && !attrs.untagged
+            && serde.serialize_with.is_none()
+            && attrs.doc_fn.is_none()
+            && attrs.example.is_none()
+            && attrs.i18n_key.is_none()
+            && ["{type}", "{env}", "{default}"]
+                .iter()
+                .all(|var| !field_docs.uses(var))
+        {
+            let docs = field_docs.tokens();
+            let type_hint = match &type_hint {
+                Some(hint) => quote! { Some(#hint) },
+                None => quote! { None },
+            };
+            let format = attrs
+                .number_format()
+                .unwrap_or_else(|| quote! { toml_comment::NumberFormat::DEFAULT });
+            let value = to_value(quote! { &this.#field_name }, &field.ty, &serde);
+            leaves.push(quote! {
+                toml_comment::Leaf {
+                    key: #field_name_str,
+                    docs: #docs,
+                    notes: &[#(#notes),*],
+                    type_hint: #type_hint,
+                    annotations: &[#(#annotation_lines),*],
+                    format: #format,
+                    value: |this: &#name| #value,
+                    default: || {
+                        #[allow(unused_imports)]
+                        use toml_comment::{NoDefault as _, ViaDefault as _};
+                        (&&toml_comment::DefaultOf::<#name>::new())
+                            .value()
+                            .and_then(|default| #default_field.ok())
+                    },
+                }
+            });
+            let i = leaves.len() - 1;
+            match leaf_run {
+                Some((start, at)) if at + 1 == render_body.len() => {
+                    render_body.pop();
+                    render_body.push(quote! {
+                        for leaf in &LEAVES[#start..=#i] {
+                            toml_comment::push_leaf(out, opts, self, leaf);
+                        }
+                    });
+                }
+                _ => {
+                    leaf_run = Some((i, render_body.len()));
+                    render_body.push(quote! {
+                        toml_comment::push_leaf(out, opts, self, &LEAVES[#i]);
+                    });
+                }
+            }
         } else {
-            render_body.extend(emit_docs(&field_docs));
+            render_body.push(doc_tokens);
+            render_body.push(quote! {
+                let val = #field_value.unwrap();
+                toml_comment::push_entry(out, &#key, &val, &#number_format, opts);
+            });
+        }
+        if doc_hidden {
+            let field_render: Vec<_> = render_body.drain(render_start..).collect();
+            render_body.push(quote! {
+                if !opts.skip_hidden {
+                    #(#field_render)*
+                }
+            });
+        }
+        if !cfgs.is_empty() {
+            let field_render: Vec<_> = render_body.drain(render_start..).collect();
             render_body.push(quote! {
-                let val = toml::Value::try_from(&self.#field_name).unwrap();
-                out.push_str(&format!("{} = {}\n", #field_name_str, toml_comment::fmt_value(&val)));
+                #(#cfgs)*
+                {
+                    #(#field_render)*
+                }
+            });
+            let field_meta: Vec<_> = fields_body.drain(fields_start..).collect();
+            fields_body.push(quote! {
+                #(#cfgs)*
+                {
+                    #(#field_meta)*
+                }
+            });
+        }
+        if attrs.flatten {
+            section_arms.push(quote! {
+                #(#cfgs)*
+                if self.#field_name._render_section(out, prefix, path, #flatten_opts) {
+                    return true;
+                }
+            });
+        } else if is_section
+            || is_tables
+            || is_dynamic
+            || (is_map && (attrs.map_tables || is_optional))
+        {
+            let field_render = &render_body[render_start..];
+            let descend = if !is_section {
+                TokenStream2::new()
+            } else {
+                let target = if optional_section {
+                    quote! { let Some(value) = &self.#field_name else { return false; }; }
+                } else {
+                    quote! { let value = &self.#field_name; }
+                };
+                quote! {
+                    if let Some(rest) = path.strip_prefix(&format!("{}.", key)) {
+                        let section = prefix.join(&key);
+                        #target
+                        return value._render_section(out, section, rest, #nested_opts);
+                    }
+                }
+            };
+            let unit_check = if is_section {
+                quote! {
+                    if #unit_variant.is_some() {
+                        return false;
+                    }
+                }
+            } else {
+                TokenStream2::new()
+            };
+            section_arms.push(quote! {
+                #(#cfgs)*
+                {
+                    #unit_check
+                    let key = #key;
+                    if path == key {
+                        #(#field_render)*
+                        return true;
+                    }
+                    #descend
+                }
             });
         }
+        // A header or `key = ` line, with a guess at the value's length.
+        let line_len = field_name_str.len() + docs_len + ENTRY_GUESS;
+        let size = if attrs.flatten {
+            quote! { self.#field_name._size_hint() }
+        } else if optional_section {
+            quote! { #line_len + self.#field_name.as_ref().map_or(0, |value| value._size_hint()) }
+        } else if is_section {
+            quote! { #line_len + self.#field_name._size_hint() }
+        } else if is_tables {
+            quote! { #line_len + #items.map(|item| #line_len + item._size_hint()).sum::<usize>() }
+        } else if is_map {
+            quote! { #line_len + #items.count() * #ENTRY_GUESS }
+        } else if is_seq_type(&field.ty) {
+            quote! { #line_len + self.#field_name.len() * #ELEMENT_GUESS }
+        } else {
+            quote! { #line_len }
+        };
+        size_body.push(quote! {
+            #(#cfgs)*
+            {
+                hint += #size;
+            }
+        });
     }
 
+    if let Some(errors) = missing_docs {
+        return errors.to_compile_error().into();
+    }
+    let at = first_table_at.unwrap_or(render_body.len());
+    render_body.splice(at..at, early_body);
+
+    let leaf_table = if leaves.is_empty() {
+        TokenStream2::new()
+    } else {
+        quote! { const LEAVES: &[toml_comment::Leaf<#name>] = &[#(#leaves),*]; }
+    };
+    let struct_docs_len: usize = struct_docs.iter().map(|doc| doc.len() + 3).sum();
+    let render_options = container.render_options();
     quote! {
+        #[allow(dead_code)]
+        const _: () = {
+            fn assert_serialize<T: ?Sized + serde::Serialize>() {}
+            fn assert_toml_comment<T: toml_comment::TomlComment>() {}
+            fn check() {
+                #(#bound_checks)*
+            }
+        };
+
         impl toml_comment::TomlComment for #name {
-            fn default_toml() -> String {
-                Self::default().to_commented_toml()
+            #render_options
+
+            fn _type_name() -> &'static str {
+                stringify!(#name)
             }
 
-            fn to_commented_toml(&self) -> String {
-                let mut out = String::new();
-                self._render(&mut out, "");
-                out
+            fn _render_docs(&self, out: &mut String, opts: &toml_comment::RenderOptions) {
+                #struct_doc_tokens
             }
 
-            fn _render(&self, out: &mut String, prefix: &str) {
+            fn _render(&self, out: &mut String, prefix: toml_comment::TablePath, opts: &toml_comment::RenderOptions) {
+                #leaf_table
                 #(#render_body)*
             }
+
+            fn _fields(&self, out: &mut Vec<toml_comment::FieldInfo>, prefix: &str) {
+                #(#fields_body)*
+            }
+
+            fn _render_section(
+                &self,
+                out: &mut String,
+                prefix: toml_comment::TablePath,
+                path: &str,
+                opts: &toml_comment::RenderOptions,
+            ) -> bool {
+                #(#section_arms)*
+                false
+            }
+
+            fn _size_hint(&self) -> usize {
+                #[allow(unused_mut)]
+                let mut hint: usize = #struct_docs_len;
+                #(#size_body)*
+                hint
+            }
         }
     }
     .into()
 }
 
+/// Bytes `_size_hint` allows for a key line beyond its key and docs, and
+/// for each element of a sequence.
+const ENTRY_GUESS: usize = 16;
+const ELEMENT_GUESS: usize = 8;
+
+/// `toml::Value::try_from(value)`, going through the field's
+/// `#[serde(with)]`/`serialize_with` function when it has one so the value
+/// matches what serde writes for the field. `HashSet`s and `BinaryHeap`s
+/// come out sorted.
+fn to_value(value: TokenStream2, ty: &Type, serde: &SerdeAttrs) -> TokenStream2 {
+    let Some(serialize_with) = &serde.serialize_with else {
+        if is_unordered_type(option_inner(ty).unwrap_or(ty)) {
+            return quote! { toml::Value::try_from(#value).map(toml_comment::sort_set) };
+        }
+        return quote! { toml::Value::try_from(#value) };
+    };
+    quote! {
+        {
+            struct SerializeWith<'a>(&'a #ty);
+
+            impl serde::Serialize for SerializeWith<'_> {
+                fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    #serialize_with(self.0, serializer)
+                }
+            }
+
+            toml::Value::try_from(SerializeWith(#value))
+        }
+    }
+}
+
+/// Enums render as the body of their section: the selected variant's fields
+/// and comments, laid out the way serde's representation of the enum reads
+/// them (see `EnumRepr`).
+fn derive_enum(input: &DeriveInput, data: &syn::DataEnum) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let container = ContainerAttrs::parse(&input.attrs)?;
+    if container.deny_missing_docs {
+        return Err(syn::Error::new_spanned(
+            name,
+            "`deny_missing_docs` is only supported on structs",
+        ));
+    }
+    let serde = SerdeAttrs::parse(&input.attrs)?;
+    let repr = match (&serde.tag, &serde.content, serde.untagged) {
+        (_, _, true) => EnumRepr::Untagged,
+        (Some(tag), Some(content), _) => EnumRepr::Adjacent(tag.clone(), content.clone()),
+        (Some(tag), None, _) => EnumRepr::Internal(tag.clone()),
+        (None, _, _) => EnumRepr::External,
+    };
+    let enum_doc_tokens = emit_type_docs(extract_docs(&input.attrs), container.i18n_key.as_deref());
+
+    let mut render_arms = Vec::new();
+    let mut fields_arms = Vec::new();
+    // Each variant built from its fields' defaults, for `variant_samples`;
+    // variants with a field that has no `Default` are left out.
+    let mut sample_arms = Vec::new();
+    let mut unit_arms = Vec::new();
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let tag_value = match SerdeAttrs::parse(&variant.attrs)?.rename {
+            Some(rename) => rename,
+            None => match &serde.rename_all {
+                Some(rule) => rule.apply_to_variant(&ident.to_string()),
+                None => ident.to_string(),
+            },
+        };
+        let variant_docs = extract_docs(&variant.attrs);
+        let variant_doc_tokens =
+            emit_docs(&FieldDocs::Own(variant_docs.clone()), None, quote! { &[] });
+        let tag_entry = |tag: &str| {
+            (
+                quote! {
+                    #variant_doc_tokens
+                    toml_comment::push_entry(
+                        out,
+                        &format!("{}{}", opts.key_prefix, #tag),
+                        &toml::Value::String(#tag_value.to_string()),
+                        &toml_comment::NumberFormat::default(),
+                        opts,
+                    );
+                },
+                quote! {
+                    out.push(toml_comment::FieldInfo {
+                        path: path(#tag),
+                        ty: "String",
+                        kind: toml_comment::FieldKind::Value,
+                        docs: &[#(#variant_docs),*],
+                        value: Some(toml::Value::String(#tag_value.to_string())),
+                        env: None,
+                        optional: false,
+                        unit: None,
+                        secret: false,
+                        min: None,
+                        max: None,
+                        choices: &[],
+                        example: None,
+                        notes: &[],
+                        aliases: &[],
+                        requires: &[],
+                        conflicts_with: &[],
+                    });
+                },
+            )
+        };
+        // What comes before the variant's fields, and where they go: the tag
+        // line for internal and adjacent tagging, a `[prefix.Variant]` table
+        // for the external default, nothing but the docs for untagged.
+        let (head_render, head_field, inner_prefix, inner_opts, inner_fields_prefix, content_path) =
+            match &repr {
+                EnumRepr::Internal(tag) => {
+                    let (render, field) = tag_entry(tag);
+                    (
+                        render,
+                        field,
+                        quote! { prefix },
+                        quote! { opts },
+                        quote! { prefix },
+                        TokenStream2::new(),
+                    )
+                }
+                // Adjacently tagged enums keep the variant's fields in a
+                // table of their own, named by `#[serde(content)]`.
+                EnumRepr::Adjacent(tag, content) => {
+                    let (mut render, field) = tag_entry(tag);
+                    let is_unit = matches!(variant.fields, Fields::Unit);
+                    if !is_unit {
+                        render.extend(quote! {
+                            let content = toml_comment::fmt_key(&toml_comment::prefixed_key(opts.key_prefix, #content));
+                            let section = prefix.join(&content);
+                            if !toml_comment::check_depth(opts, section) {
+                                return Default::default();
+                            }
+                            toml_comment::push_gap(out, opts);
+                            toml_comment::push_header(out, opts, &section, false);
+                        });
+                    }
+                    (
+                        render,
+                        field,
+                        quote! { section },
+                        quote! { &toml_comment::RenderOptions { key_prefix: "", in_table: true, ..*opts } },
+                        quote! { &path(#content) },
+                        quote! {
+                            let content = path(#content);
+                            let path = |key: &str| format!("{}.{}", content, key);
+                        },
+                    )
+                }
+                EnumRepr::External => {
+                    let variant_name = ident.to_string();
+                    (
+                        quote! {
+                            let variant = toml_comment::fmt_key(&toml_comment::prefixed_key(opts.key_prefix, #tag_value));
+                            let section = prefix.join(&variant);
+                            if !toml_comment::check_depth(opts, section) {
+                                return Default::default();
+                            }
+                            if !out.is_empty() {
+                                toml_comment::push_gap(out, opts);
+                            }
+                            {
+                                let opts = &toml_comment::RenderOptions { in_table: false, ..*opts };
+                                #variant_doc_tokens
+                            }
+                            toml_comment::push_header(out, opts, &section, false);
+                        },
+                        quote! {
+                            out.push(toml_comment::FieldInfo {
+                                path: path(#tag_value),
+                                ty: #variant_name,
+                                kind: toml_comment::FieldKind::Section,
+                                docs: &[#(#variant_docs),*],
+                                value: None,
+                                env: None,
+                                optional: false,
+                                unit: None,
+                                secret: false,
+                                min: None,
+                                max: None,
+                                choices: &[],
+                                example: None,
+                                notes: &[],
+                                aliases: &[],
+                                requires: &[],
+                                conflicts_with: &[],
+                            });
+                        },
+                        quote! { section },
+                        quote! { &toml_comment::RenderOptions { key_prefix: "", in_table: true, ..*opts } },
+                        quote! { &path(#tag_value) },
+                        quote! {
+                            let variant = path(#tag_value);
+                            let path = |key: &str| format!("{}.{}", variant, key);
+                        },
+                    )
+                }
+                EnumRepr::Untagged => {
+                    if matches!(variant.fields, Fields::Unit) {
+                        return Err(syn::Error::new_spanned(
+                            variant,
+                            "unit variants of untagged enums have no TOML form",
+                        ));
+                    }
+                    (
+                        variant_doc_tokens,
+                        TokenStream2::new(),
+                        quote! { prefix },
+                        quote! { opts },
+                        quote! { prefix },
+                        TokenStream2::new(),
+                    )
+                }
+            };
+        match &variant.fields {
+            Fields::Unit => {
+                render_arms.push(quote! { Self::#ident => { #head_render } });
+                fields_arms.push(quote! { Self::#ident => { #head_field } });
+                sample_arms.push(quote! { samples.push(Self::#ident); });
+                unit_arms.push(quote! {
+                    Self::#ident => Some((#tag_value, &[#(#variant_docs),*])),
+                });
+            }
+            Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+                render_arms.push(quote! {
+                    Self::#ident(inner) => {
+                        #head_render
+                        inner._render(out, #inner_prefix, #inner_opts);
+                    }
+                });
+                fields_arms.push(quote! {
+                    Self::#ident(inner) => {
+                        #head_field
+                        inner._fields(out, #inner_fields_prefix);
+                    }
+                });
+                let ty = &unnamed.unnamed[0].ty;
+                sample_arms.push(quote! {
+                    if let Some(inner) = (&&toml_comment::DefaultOf::<#ty>::new()).value() {
+                        samples.push(Self::#ident(inner));
+                    }
+                });
+            }
+            Fields::Named(named) => {
+                // Bound to positional names so fields can't shadow `out`,
+                // `opts` or `prefix`.
+                let mut bindings = Vec::new();
+                let mut render = Vec::new();
+                let mut fields = Vec::new();
+                for (i, field) in named.named.iter().enumerate() {
+                    let ident = field.ident.as_ref().expect("named field");
+                    let field_name = quote::format_ident!("field_{}", i);
+                    bindings.push(quote! { #ident: #field_name });
+                    let field_name_str = match SerdeAttrs::parse(&field.attrs)?.rename {
+                        Some(rename) => rename,
+                        None => ident.unraw().to_string(),
+                    };
+                    let docs = if is_doc_hidden(&field.attrs) {
+                        Vec::new()
+                    } else {
+                        extract_docs(&field.attrs)
+                    };
+                    let doc_tokens = emit_docs(&FieldDocs::Own(docs.clone()), None, quote! { &[] });
+                    let line = quote! {
+                        #doc_tokens
+                        let val = toml::Value::try_from(#field_name).unwrap();
+                        let key = format!("{}{}", opts.key_prefix, #field_name_str);
+                        toml_comment::push_entry(out, &key, &val, &toml_comment::NumberFormat::default(), opts);
+                    };
+                    if is_option_type(&field.ty) {
+                        render.push(quote! {
+                            if #field_name.is_some() {
+                                #line
+                            }
+                        });
+                    } else {
+                        render.push(line);
+                    }
+                    let ty_name = type_name(&field.ty);
+                    let optional = is_option_type(&field.ty);
+                    fields.push(quote! {
+                        out.push(toml_comment::FieldInfo {
+                            path: path(#field_name_str),
+                            ty: #ty_name,
+                            kind: toml_comment::FieldKind::Value,
+                            docs: &[#(#docs),*],
+                            value: toml::Value::try_from(#field_name).ok(),
+                            env: None,
+                            optional: #optional,
+                            unit: None,
+                            secret: false,
+                            min: None,
+                            max: None,
+                            choices: &[],
+                            example: None,
+                            notes: &[],
+                            aliases: &[],
+                            requires: &[],
+                            conflicts_with: &[],
+                        });
+                    });
+                }
+                render_arms.push(quote! {
+                    Self::#ident { #(#bindings),* } => {
+                        #head_render
+                        let opts = #inner_opts;
+                        #(#render)*
+                    }
+                });
+                fields_arms.push(quote! {
+                    Self::#ident { #(#bindings),* } => {
+                        #head_field
+                        #content_path
+                        #(#fields)*
+                    }
+                });
+                let names: Vec<_> = (0..named.named.len())
+                    .map(|i| quote::format_ident!("field_{}", i))
+                    .collect();
+                let types = named.named.iter().map(|field| &field.ty);
+                sample_arms.push(quote! {
+                    if let (#(Some(#names),)*) = (#((&&toml_comment::DefaultOf::<#types>::new()).value(),)*) {
+                        samples.push(Self::#ident { #(#bindings),* });
+                    }
+                });
+            }
+            Fields::Unnamed(_) => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "TomlComment enums support unit, struct and single-field tuple variants",
+                ));
+            }
+        }
+    }
+
+    // serde writes an externally tagged unit variant as its bare name.
+    let unit_variant = if matches!(repr, EnumRepr::External) && !unit_arms.is_empty() {
+        quote! {
+            fn _unit_variant(&self) -> Option<(&'static str, &'static [&'static str])> {
+                match self {
+                    #(#unit_arms)*
+                    _ => None,
+                }
+            }
+        }
+    } else {
+        TokenStream2::new()
+    };
+    let render_options = container.render_options();
+    Ok(quote! {
+        impl toml_comment::TomlComment for #name {
+            #render_options
+
+            fn _type_name() -> &'static str {
+                stringify!(#name)
+            }
+
+            #unit_variant
+
+            fn _render_docs(&self, out: &mut String, opts: &toml_comment::RenderOptions) {
+                #enum_doc_tokens
+            }
+
+            fn _render(&self, out: &mut String, prefix: toml_comment::TablePath, opts: &toml_comment::RenderOptions) {
+                match self {
+                    #(#render_arms)*
+                }
+            }
+
+            fn _fields(&self, out: &mut Vec<toml_comment::FieldInfo>, prefix: &str) {
+                let path = |key: &str| {
+                    if prefix.is_empty() {
+                        key.to_string()
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    }
+                };
+                match self {
+                    #(#fields_arms)*
+                }
+            }
+
+            fn _variant_samples() -> (Vec<Self>, Option<usize>) {
+                #[allow(unused_imports)]
+                use toml_comment::{NoDefault as _, ViaDefault as _};
+                let mut samples = Vec::new();
+                #(#sample_arms)*
+                // The default takes its variant's place, with its own values.
+                let default = (&&toml_comment::DefaultOf::<Self>::new())
+                    .value()
+                    .map(|default| {
+                        let same = samples.iter().position(|sample| {
+                            std::mem::discriminant(sample) == std::mem::discriminant(&default)
+                        });
+                        match same {
+                            Some(i) => {
+                                samples[i] = default;
+                                i
+                            }
+                            None => {
+                                samples.push(default);
+                                samples.len() - 1
+                            }
+                        }
+                    });
+                (samples, default)
+            }
+        }
+    })
+}
+
+/// How serde lays out an enum, from its container attributes.
+enum EnumRepr {
+    /// The default: `{ Variant = { ... } }`.
+    External,
+    /// `#[serde(tag = "...")]`
+    Internal(String),
+    /// `#[serde(tag = "...", content = "...")]`
+    Adjacent(String, String),
+    /// `#[serde(untagged)]`
+    Untagged,
+}
+
+fn type_name(ty: &Type) -> String {
+    quote!(#ty)
+        .to_string()
+        .replace(" <", "<")
+        .replace("< ", "<")
+        .replace(" >", ">")
+        .replace(" ,", ",")
+        .replace(" :: ", "::")
+}
+
+/// How a Rust type reads in TOML terms (`integer`, `array of strings`), for
+/// the `type_hints` render option. `None` for types it can't tell.
+fn toml_type_name(ty: &Type) -> Option<String> {
+    if let Some(inner) = option_inner(ty).or_else(|| box_inner(ty)) {
+        return toml_type_name(inner);
+    }
+    if let Type::Array(array) = ty {
+        return toml_type_name(&array.elem).map(|elem| format!("array of {}", plural(&elem)));
+    }
+    if let Type::Tuple(tuple) = ty {
+        let elems: Option<Vec<String>> = tuple.elems.iter().map(toml_type_name).collect();
+        return elems.map(|elems| format!("array [{}]", elems.join(", ")));
+    }
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let seg = type_path.path.segments.last()?;
+    let name = match seg.ident.to_string().as_str() {
+        "bool" => "boolean",
+        "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128" | "usize"
+        | "isize" => "integer",
+        "f32" | "f64" | "OrderedFloat" | "NotNan" => "float",
+        "String" | "PathBuf" | "char" => "string",
+        "HashMap" | "BTreeMap" => "table",
+        "HashSet" | "BTreeSet" => {
+            let PathArguments::AngleBracketed(args) = &seg.arguments else {
+                return None;
+            };
+            let Some(syn::GenericArgument::Type(elem)) = args.args.first() else {
+                return None;
+            };
+            let elem = toml_type_name(elem)?;
+            return Some(format!("array of {}", plural(&elem)));
+        }
+        _ if is_seq_type(ty) => {
+            let elem = toml_type_name(seq_inner(ty)?)?;
+            return Some(format!("array of {}", plural(&elem)));
+        }
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+fn plural(name: &str) -> String {
+    match name.split_once(' ') {
+        Some((head, rest)) => format!("{head}s {rest}"),
+        None => format!("{name}s"),
+    }
+}
+
+fn is_doc_hidden(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("doc")
+            && matches!(&attr.meta, syn::Meta::List(list) if list.tokens.to_string().trim() == "hidden")
+    })
+}
+
 fn extract_docs(attrs: &[syn::Attribute]) -> Vec<String> {
     attrs
         .iter()
@@ -143,11 +1752,438 @@ fn extract_docs(attrs: &[syn::Attribute]) -> Vec<String> {
         .collect()
 }
 
-fn has_toml_comment_attr(attrs: &[syn::Attribute], name: &str) -> bool {
-    attrs.iter().any(|attr| {
-        attr.path().is_ident("toml_comment")
-            && matches!(&attr.meta, syn::Meta::List(list) if list.tokens.to_string().trim() == name)
-    })
+#[derive(Default)]
+struct ContainerAttrs {
+    i18n_key: Option<String>,
+    rename_sections: Option<RenameRule>,
+    /// `RenderOptions` fields set by `style(...)`, as struct literal
+    /// entries.
+    style: Vec<TokenStream2>,
+    /// The `RenderOptions` constructor named by `style(preset = "...")`.
+    preset: Option<syn::Ident>,
+    deny_missing_docs: bool,
+}
+
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(lit: &syn::LitStr) -> syn::Result<Self> {
+        Ok(match lit.value().as_str() {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "PascalCase" => Self::Pascal,
+            "camelCase" => Self::Camel,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            _ => return Err(syn::Error::new_spanned(lit, "unknown rename rule")),
+        })
+    }
+
+    fn apply(&self, field: &str) -> String {
+        let words = field.split('_').filter(|word| !word.is_empty());
+        let capitalize = |word: &str| -> String {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        };
+        match self {
+            Self::Lower => field.replace('_', "").to_lowercase(),
+            Self::Upper => field.replace('_', "").to_uppercase(),
+            Self::Pascal => words.map(capitalize).collect(),
+            Self::Camel => {
+                let pascal: String = words.map(capitalize).collect();
+                let mut chars = pascal.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_lowercase().chain(chars).collect())
+                    .unwrap_or_default()
+            }
+            Self::Snake => field.to_string(),
+            Self::ScreamingSnake => field.to_uppercase(),
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => field.replace('_', "-").to_uppercase(),
+        }
+    }
+
+    /// Like [`Self::apply`], for `PascalCase` variant names.
+    fn apply_to_variant(&self, variant: &str) -> String {
+        match self {
+            Self::Pascal => variant.to_string(),
+            Self::Lower => variant.to_ascii_lowercase(),
+            Self::Upper => variant.to_ascii_uppercase(),
+            Self::Camel => {
+                let mut chars = variant.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            }
+            _ => {
+                let mut snake = String::new();
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                self.apply(&snake)
+            }
+        }
+    }
+}
+
+/// The `#[serde(...)]` options the rendered TOML has to agree with; anything
+/// else serde accepts is skipped.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    tag: Option<String>,
+    content: Option<String>,
+    untagged: bool,
+    default: bool,
+    aliases: Vec<String>,
+    serialize_with: Option<syn::Path>,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut out = Self::default();
+        for attr in attrs {
+            if !attr.path().is_ident("serde") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                let has_value = meta.input.peek(syn::Token![=]);
+                if meta.path.is_ident("rename") && has_value {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.rename = Some(lit.value());
+                } else if meta.path.is_ident("rename_all") && has_value {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.rename_all = Some(RenameRule::parse(&lit)?);
+                } else if meta.path.is_ident("tag") && has_value {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.tag = Some(lit.value());
+                } else if meta.path.is_ident("alias") && has_value {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.aliases.push(lit.value());
+                } else if meta.path.is_ident("content") && has_value {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.content = Some(lit.value());
+                } else if meta.path.is_ident("with") && has_value {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    let mut path: syn::Path = lit.parse()?;
+                    path.segments.push(quote::format_ident!("serialize").into());
+                    out.serialize_with = Some(path);
+                } else if meta.path.is_ident("serialize_with") && has_value {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.serialize_with = Some(lit.parse()?);
+                } else if meta.path.is_ident("untagged") {
+                    out.untagged = true;
+                } else if meta.path.is_ident("default") {
+                    out.default = true;
+                    if has_value {
+                        meta.value()?.parse::<syn::Expr>()?;
+                    }
+                } else if has_value {
+                    meta.value()?.parse::<syn::Expr>()?;
+                } else if meta.input.peek(syn::token::Paren) {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    content.parse::<TokenStream2>()?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+impl ContainerAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut out = Self::default();
+        for attr in attrs {
+            if !attr.path().is_ident("toml_comment") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("i18n_key") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.i18n_key = Some(lit.value());
+                } else if meta.path.is_ident("rename_sections") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.rename_sections = Some(RenameRule::parse(&lit)?);
+                } else if meta.path.is_ident("deny_missing_docs") {
+                    out.deny_missing_docs = true;
+                } else if meta.path.is_ident("style") {
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("preset") {
+                            let lit: syn::LitStr = meta.value()?.parse()?;
+                            if !PRESETS.contains(&lit.value().as_str()) {
+                                return Err(syn::Error::new_spanned(
+                                    &lit,
+                                    format!(
+                                        "unknown preset, expected one of: {}",
+                                        PRESETS.join(", ")
+                                    ),
+                                ));
+                            }
+                            out.preset = Some(syn::Ident::new(&lit.value(), lit.span()));
+                        } else {
+                            out.style.push(parse_style_option(&meta)?);
+                        }
+                        Ok(())
+                    })?;
+                } else {
+                    return Err(meta.error("unknown toml_comment attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
+
+    /// A `render_options()` override when the type sets a style.
+    fn render_options(&self) -> TokenStream2 {
+        if self.style.is_empty() && self.preset.is_none() {
+            return TokenStream2::new();
+        }
+        let style = &self.style;
+        let base = match &self.preset {
+            Some(preset) => quote! { toml_comment::RenderOptions::#preset() },
+            None => quote! { Default::default() },
+        };
+        quote! {
+            fn render_options() -> toml_comment::RenderOptions<'static> {
+                toml_comment::RenderOptions {
+                    #(#style,)*
+                    ..#base
+                }
+            }
+        }
+    }
+}
+
+/// `RenderOptions` constructors `style(preset = "...")` accepts.
+const PRESETS: &[&str] = &["compact", "documented", "verbose", "example", "taplo"];
+
+/// One `name` or `name = value` inside `style(...)`, checked against the
+/// type of the `RenderOptions` field it sets.
+fn parse_style_option(meta: &syn::meta::ParseNestedMeta) -> syn::Result<TokenStream2> {
+    let Some(name) = meta.path.get_ident().cloned() else {
+        return Err(meta.error("unknown style option"));
+    };
+    let has_value = meta.input.peek(syn::Token![=]);
+    match name.to_string().as_str() {
+        "skip_hidden" | "strip_markdown" | "mark_optional" | "show_unset" | "type_hints"
+        | "list_choices" | "annotate" | "scientific_floats" | "blank_after_docs"
+        | "toml_pretty" => {
+            let value = if has_value {
+                meta.value()?.parse::<syn::LitBool>()?.value
+            } else {
+                true
+            };
+            Ok(quote! { #name: #value })
+        }
+        "indent" => {
+            let lit: syn::LitInt = meta.value()?.parse()?;
+            let value: usize = lit.base10_parse()?;
+            Ok(quote! { #name: #value })
+        }
+        "max_depth" | "banner_width" | "section_gap" | "line_width" | "array_indent" => {
+            let lit: syn::LitInt = meta.value()?.parse()?;
+            let value: usize = lit.base10_parse()?;
+            Ok(quote! { #name: Some(#value) })
+        }
+        "comment_prefix" | "type_comment_prefix" => {
+            let lit: syn::LitStr = meta.value()?.parse()?;
+            Ok(quote! { #name: Some(#lit) })
+        }
+        _ => Err(meta.error("unknown style option")),
+    }
+}
+
+#[derive(Default, PartialEq)]
+enum SectionDocs {
+    Above,
+    #[default]
+    Inside,
+    Hidden,
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    inline: bool,
+    env: Option<String>,
+    i18n_key: Option<String>,
+    section_docs: SectionDocs,
+    type_docs: bool,
+    flatten: bool,
+    prefix: Option<String>,
+    key_docs: Option<syn::Path>,
+    doc_fn: Option<syn::Path>,
+    map_tables: bool,
+    untagged: bool,
+    unit: Option<String>,
+    elements: Option<String>,
+    placeholder: Option<String>,
+    secret: bool,
+    min: Option<syn::Lit>,
+    max: Option<syn::Lit>,
+    choices: bool,
+    example: Option<syn::Lit>,
+    see: Vec<String>,
+    requires: Vec<String>,
+    conflicts_with: Vec<String>,
+    radix: Option<u32>,
+    separators: bool,
+    precision: Option<usize>,
+    scientific: bool,
+}
+
+impl FieldAttrs {
+    /// A `toml_comment::NumberFormat` for fields that ask for one.
+    fn number_format(&self) -> Option<TokenStream2> {
+        if self.radix.is_none() && !self.separators && self.precision.is_none() && !self.scientific
+        {
+            return None;
+        }
+        let radix = self.radix.unwrap_or(10);
+        let separators = self.separators;
+        let scientific = self.scientific;
+        let precision = match self.precision {
+            Some(precision) => quote! { Some(#precision) },
+            None => quote! { None },
+        };
+        Some(quote! {
+            toml_comment::NumberFormat {
+                radix: #radix,
+                separators: #separators,
+                precision: #precision,
+                scientific: #scientific,
+            }
+        })
+    }
+
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut out = Self::default();
+        for attr in attrs {
+            if !attr.path().is_ident("toml_comment") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("inline") {
+                    out.inline = true;
+                } else if meta.path.is_ident("env") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.env = Some(lit.value());
+                } else if meta.path.is_ident("i18n_key") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.i18n_key = Some(lit.value());
+                } else if meta.path.is_ident("untagged") {
+                    out.untagged = true;
+                } else if meta.path.is_ident("format") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.radix = Some(match lit.value().as_str() {
+                        "hex" => 16,
+                        "octal" => 8,
+                        "binary" => 2,
+                        "decimal" => 10,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected \"hex\", \"octal\", \"binary\" or \"decimal\"",
+                            ));
+                        }
+                    });
+                } else if meta.path.is_ident("precision") {
+                    let lit: syn::LitInt = meta.value()?.parse()?;
+                    out.precision = Some(lit.base10_parse()?);
+                } else if meta.path.is_ident("scientific") {
+                    out.scientific = true;
+                } else if meta.path.is_ident("separators") {
+                    out.separators = true;
+                } else if meta.path.is_ident("requires") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.requires.push(lit.value());
+                } else if meta.path.is_ident("conflicts_with") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.conflicts_with.push(lit.value());
+                } else if meta.path.is_ident("see") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.see.push(lit.value());
+                } else if meta.path.is_ident("example") {
+                    out.example = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("secret") {
+                    out.secret = true;
+                } else if meta.path.is_ident("choices") {
+                    out.choices = true;
+                } else if meta.path.is_ident("min") || meta.path.is_ident("max") {
+                    let lit: syn::Lit = meta.value()?.parse()?;
+                    if !matches!(lit, syn::Lit::Int(_) | syn::Lit::Float(_)) {
+                        return Err(syn::Error::new_spanned(lit, "expected a number"));
+                    }
+                    if meta.path.is_ident("min") {
+                        out.min = Some(lit);
+                    } else {
+                        out.max = Some(lit);
+                    }
+                } else if meta.path.is_ident("unit") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.unit = Some(lit.value());
+                } else if meta.path.is_ident("placeholder") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.placeholder = Some(lit.value());
+                } else if meta.path.is_ident("elements") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.elements = Some(lit.value());
+                } else if meta.path.is_ident("map_tables") {
+                    out.map_tables = true;
+                } else if meta.path.is_ident("doc_fn") {
+                    out.doc_fn = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("key_docs") {
+                    out.key_docs = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("flatten") {
+                    out.flatten = true;
+                } else if meta.path.is_ident("prefix") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.prefix = Some(lit.value());
+                } else if meta.path.is_ident("type_docs") {
+                    out.type_docs = true;
+                } else if meta.path.is_ident("section_docs") {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    out.section_docs = match lit.value().as_str() {
+                        "above" => SectionDocs::Above,
+                        "inside" => SectionDocs::Inside,
+                        "hidden" => SectionDocs::Hidden,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected \"above\", \"inside\" or \"hidden\"",
+                            ));
+                        }
+                    };
+                } else {
+                    return Err(meta.error("unknown toml_comment attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
 }
 
 fn is_section_type(ty: &Type) -> bool {
@@ -165,6 +2201,118 @@ fn is_section_type(ty: &Type) -> bool {
     !LEAF_TYPES.contains(&seg.ident.to_string().as_str())
 }
 
+/// `serde_json::Value`, `toml::Value` or `toml::Table`, written with their
+/// crate path so a local `Value` type is not mistaken for them.
+fn is_dynamic_type(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    let segments: Vec<String> = type_path
+        .path
+        .segments
+        .iter()
+        .map(|seg| seg.ident.to_string())
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    matches!(
+        segments.as_slice(),
+        [.., "serde_json", "Value"]
+            | [.., "toml", "Value" | "Table"]
+            | [.., "toml", "value", "Table"]
+    )
+}
+
+fn is_phantom_data(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    type_path
+        .path
+        .segments
+        .last()
+        .is_some_and(|seg| seg.ident == "PhantomData")
+}
+
+fn is_unit_type(ty: &Type) -> bool {
+    matches!(ty, Type::Tuple(tuple) if tuple.elems.is_empty())
+}
+
+fn box_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let seg = type_path.path.segments.last()?;
+    if seg.ident != "Box" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &seg.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+/// Sequences serde writes as arrays.
+fn is_seq_type(ty: &Type) -> bool {
+    if let Type::Array(_) = ty {
+        return true;
+    }
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    type_path.path.segments.last().is_some_and(|seg| {
+        matches!(
+            seg.ident.to_string().as_str(),
+            "Vec" | "VecDeque" | "LinkedList" | "BinaryHeap" | "SmallVec" | "ArrayVec"
+        )
+    })
+}
+
+/// The element type of a sequence; `SmallVec<[T; N]>` names it inside
+/// its backing array.
+fn seq_inner(ty: &Type) -> Option<&Type> {
+    if !is_seq_type(ty) {
+        return None;
+    }
+    if let Type::Array(array) = ty {
+        return Some(&array.elem);
+    }
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let PathArguments::AngleBracketed(args) = &type_path.path.segments.last()?.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(Type::Array(array)) => Some(&array.elem),
+        syn::GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+fn is_table_array_type(ty: &Type) -> bool {
+    seq_inner(ty).is_some_and(is_section_type)
+}
+
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let seg = type_path.path.segments.last()?;
+    if seg.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &seg.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
 fn is_option_type(ty: &Type) -> bool {
     let Type::Path(type_path) = ty else {
         return false;
@@ -175,6 +2323,29 @@ fn is_option_type(ty: &Type) -> bool {
     seg.ident == "Option"
 }
 
+fn is_hash_map_type(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    type_path
+        .path
+        .segments
+        .last()
+        .is_some_and(|seg| seg.ident == "HashMap")
+}
+
+/// Collections without a stable iteration order.
+fn is_unordered_type(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    type_path
+        .path
+        .segments
+        .last()
+        .is_some_and(|seg| seg.ident == "HashSet" || seg.ident == "BinaryHeap")
+}
+
 fn is_map_type(ty: &Type) -> bool {
     let Type::Path(type_path) = ty else {
         return false;
@@ -184,3 +2355,64 @@ fn is_map_type(ty: &Type) -> bool {
     };
     seg.ident == "HashMap" || seg.ident == "BTreeMap"
 }
+
+#[proc_macro_derive(TypeDocs)]
+pub fn derive_type_docs(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let docs = extract_docs(&input.attrs);
+    let serde = match SerdeAttrs::parse(&input.attrs) {
+        Ok(attrs) => attrs,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let forms = match &input.data {
+        Data::Enum(data) if serde.untagged => {
+            let forms = data.variants.iter().map(|variant| {
+                let docs = extract_docs(&variant.attrs);
+                match docs.first() {
+                    Some(doc) => doc.trim().to_string(),
+                    None => match &variant.fields {
+                        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+                            type_name(&unnamed.unnamed[0].ty)
+                        }
+                        _ => variant.ident.to_string(),
+                    },
+                }
+            });
+            quote! { const FORMS: &'static [&'static str] = &[#(#forms),*]; }
+        }
+        _ => TokenStream2::new(),
+    };
+    let choices = match &input.data {
+        Data::Enum(data)
+            if !serde.untagged
+                && data
+                    .variants
+                    .iter()
+                    .all(|variant| matches!(variant.fields, Fields::Unit)) =>
+        {
+            let mut choices = Vec::new();
+            for variant in &data.variants {
+                let rename = match SerdeAttrs::parse(&variant.attrs) {
+                    Ok(attrs) => attrs.rename,
+                    Err(err) => return err.to_compile_error().into(),
+                };
+                choices.push(rename.unwrap_or_else(|| match &serde.rename_all {
+                    Some(rule) => rule.apply_to_variant(&variant.ident.to_string()),
+                    None => variant.ident.to_string(),
+                }));
+            }
+            quote! { const CHOICES: &'static [&'static str] = &[#(#choices),*]; }
+        }
+        _ => TokenStream2::new(),
+    };
+    quote! {
+        impl #impl_generics toml_comment::TypeDocs for #name #ty_generics #where_clause {
+            const DOCS: &'static [&'static str] = &[#(#docs),*];
+            #forms
+            #choices
+        }
+    }
+    .into()
+}
@@ -0,0 +1,40 @@
+//! "Preview your config file": parse what the user typed and render it back
+//! with every comment filled in. Nothing here touches the filesystem, so the
+//! same function can sit behind `#[wasm_bindgen]` in a browser settings UI.
+
+use serde::{Deserialize, Serialize};
+use toml_comment::TomlComment;
+
+/// Web server settings
+#[derive(Serialize, Deserialize, TomlComment)]
+#[serde(default)]
+struct ServerConfig {
+    /// Port to listen on
+    port: u16,
+    /// Bind address
+    host: String,
+    /// Worker threads, 0 for one per core
+    workers: u32,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            host: "127.0.0.1".to_string(),
+            workers: 0,
+        }
+    }
+}
+
+fn preview(input: &str) -> String {
+    match toml::from_str::<ServerConfig>(input) {
+        Ok(config) => config.to_commented_toml(),
+        Err(err) => format!("# {}\n", err.message()),
+    }
+}
+
+fn main() {
+    print!("{}", preview("port = 3000\n"));
+    print!("{}", preview("port = \"oops\"\n"));
+}
@@ -0,0 +1,96 @@
+//! Byte counts as human-readable sizes, for `#[serde(with = "toml_comment::bytesize")]`
+//! on `u64` fields. Values are written as `"256MiB"` and read back from
+//! either that form (`"1.5 GB"`, `"512kB"`, ...) or a plain integer.
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::Serializer;
+
+const UNITS: &[(&str, u64)] = &[
+    ("TiB", 1 << 40),
+    ("GiB", 1 << 30),
+    ("MiB", 1 << 20),
+    ("KiB", 1 << 10),
+    ("TB", 1_000_000_000_000),
+    ("GB", 1_000_000_000),
+    ("MB", 1_000_000),
+    ("kB", 1_000),
+];
+
+/// The largest unit that divides `bytes` exactly, binary units first, so
+/// the string reads back to the same number.
+pub fn format(bytes: u64) -> String {
+    if bytes == 0 {
+        return "0B".to_string();
+    }
+    for (unit, size) in UNITS {
+        if bytes.is_multiple_of(*size) {
+            return format!("{}{unit}", bytes / size);
+        }
+    }
+    format!("{bytes}B")
+}
+
+/// Parses `"256MiB"`, `"1.5 GB"`, `"4096"` and the like. Units are
+/// case-insensitive; a bare number is bytes.
+pub fn parse(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.' && c != '_')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number = number.replace('_', "");
+    let unit = unit.trim();
+    let size = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1_000,
+        "m" | "mb" => 1_000_000,
+        "g" | "gb" => 1_000_000_000,
+        "t" | "tb" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return Err(format!("unknown size unit `{unit}` in `{s}`")),
+    };
+    if let Ok(whole) = number.parse::<u64>() {
+        return whole
+            .checked_mul(size)
+            .ok_or_else(|| format!("size `{s}` is too large"));
+    }
+    match number.parse::<f64>() {
+        Ok(n) if n >= 0.0 && (n * size as f64) < u64::MAX as f64 => {
+            Ok((n * size as f64).round() as u64)
+        }
+        _ => Err(format!("invalid size `{s}`")),
+    }
+}
+
+pub fn serialize<S: Serializer>(bytes: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format(*bytes))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    struct SizeVisitor;
+
+    impl Visitor<'_> for SizeVisitor {
+        type Value = u64;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a byte count or a size like \"256MiB\"")
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<u64, E> {
+            Ok(v)
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<u64, E> {
+            u64::try_from(v).map_err(|_| E::custom("size can't be negative"))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<u64, E> {
+            parse(v).map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_any(SizeVisitor)
+}
@@ -0,0 +1,22 @@
+use crate::yaml::key_yaml;
+
+/// A `ConfigMap` holding `toml` under `file_name`. The name and file name
+/// are quoted when YAML would read them as anything but a plain string.
+pub(crate) fn render(name: &str, file_name: &str, toml: &str) -> String {
+    let mut out = String::from("apiVersion: v1\nkind: ConfigMap\nmetadata:\n");
+    out.push_str(&format!("  name: {}\ndata:\n", key_yaml(name)));
+    let file_name = key_yaml(file_name);
+    if toml.is_empty() {
+        out.push_str(&format!("  {file_name}: \"\"\n"));
+        return out;
+    }
+    out.push_str(&format!("  {file_name}: |-\n"));
+    for line in toml.lines() {
+        if line.is_empty() {
+            out.push('\n');
+        } else {
+            out.push_str(&format!("    {line}\n"));
+        }
+    }
+    out
+}
@@ -0,0 +1,69 @@
+use crate::FieldInfo;
+
+/// What changed between two versions of a config, key by key, from their
+/// [`TomlComment::fields`](crate::TomlComment::fields). Each entry keeps its
+/// docs, so the report can be turned into release notes or upgrade warnings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigDiff {
+    pub added: Vec<FieldInfo>,
+    pub removed: Vec<FieldInfo>,
+    /// Keys that moved: one was removed and another added with the same
+    /// type, kind and doc comment.
+    pub renamed: Vec<KeyChange>,
+    /// Keys present in both whose value differs.
+    pub default_changed: Vec<KeyChange>,
+}
+
+/// The same key before and after.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyChange {
+    pub old: FieldInfo,
+    pub new: FieldInfo,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.default_changed.is_empty()
+    }
+}
+
+/// Compares two field lists, typically `OldConfig::default().fields()` and
+/// `Config::default().fields()`. Entries keep the order of the list they
+/// come from.
+pub fn diff_fields(old: &[FieldInfo], new: &[FieldInfo]) -> ConfigDiff {
+    let mut report = ConfigDiff::default();
+    for field in new {
+        match old.iter().find(|o| o.path == field.path) {
+            Some(o) if o.value != field.value => report.default_changed.push(KeyChange {
+                old: o.clone(),
+                new: field.clone(),
+            }),
+            Some(_) => {}
+            None => report.added.push(field.clone()),
+        }
+    }
+    for field in old {
+        if !new.iter().any(|n| n.path == field.path) {
+            report.removed.push(field.clone());
+        }
+    }
+
+    let mut removed = Vec::new();
+    for old in std::mem::take(&mut report.removed) {
+        let moved = report.added.iter().position(|new| {
+            !old.docs.is_empty() && new.docs == old.docs && new.ty == old.ty && new.kind == old.kind
+        });
+        match moved {
+            Some(i) => {
+                let new = report.added.remove(i);
+                report.renamed.push(KeyChange { old, new });
+            }
+            None => removed.push(old),
+        }
+    }
+    report.removed = removed;
+    report
+}
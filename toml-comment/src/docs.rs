@@ -0,0 +1,243 @@
+use crate::RenderOptions;
+
+/// Applies the comment-related render options to raw doc lines. Fenced code
+/// blocks pass through untouched; when markdown is stripped their fences are
+/// dropped and the contents indented instead.
+pub(crate) fn format(lines: &[String], opts: &RenderOptions) -> Vec<String> {
+    let lines = normalize(lines);
+    let mut out = Vec::with_capacity(lines.len());
+    let mut in_fence = false;
+    for line in &lines {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            if !opts.strip_markdown {
+                out.push(line.clone());
+            }
+        } else if in_fence {
+            if opts.strip_markdown && !line.is_empty() {
+                out.push(format!("    {line}"));
+            } else {
+                out.push(line.clone());
+            }
+        } else if opts.strip_markdown {
+            out.push(strip_markdown(line));
+        } else {
+            out.push(line.clone());
+        }
+    }
+    out
+}
+
+/// Writes one comment line behind `prefix`; blank lines keep only the
+/// prefix's marker so they don't end in whitespace.
+pub(crate) fn push_comment(out: &mut String, prefix: &str, line: &str) {
+    if line.is_empty() {
+        out.push_str(prefix.trim_end());
+    } else {
+        out.push_str(prefix);
+        out.push_str(line);
+    }
+    out.push('\n');
+}
+
+/// Evens out rustdoc's raw lines: the indentation shared by `///` lines
+/// (usually rustdoc's single leading space) is removed, block doc comments
+/// are split and unindented on their own, runs of blank lines collapse into
+/// one paragraph break, and `*`/`+` bullets become `-`.
+fn normalize(docs: &[String]) -> Vec<String> {
+    let indent = docs
+        .iter()
+        .filter(|doc| !doc.contains('\n'))
+        .map(|doc| doc.trim_end())
+        .filter(|doc| !doc.is_empty())
+        .map(margin)
+        .min()
+        .unwrap_or(0);
+    let mut lines: Vec<String> = Vec::with_capacity(docs.len());
+    for doc in docs {
+        if doc.contains('\n') {
+            lines.extend(unindent(doc.split('\n').collect()));
+        } else {
+            let doc = doc.trim_end();
+            lines.push(dedent(doc, indent).to_string());
+        }
+    }
+
+    let mut out: Vec<String> = Vec::with_capacity(lines.len());
+    let mut in_fence = false;
+    for line in &lines {
+        if line.is_empty() {
+            if !in_fence && out.last().is_none_or(|last| last.is_empty()) {
+                continue;
+            }
+            out.push(String::new());
+            continue;
+        }
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            out.push(line.to_string());
+        } else if !in_fence
+            && let Some(item) = trimmed
+                .strip_prefix("* ")
+                .or_else(|| trimmed.strip_prefix("+ "))
+        {
+            let bullet_indent = &line[..line.len() - trimmed.len()];
+            out.push(format!("{bullet_indent}- {item}"));
+        } else {
+            out.push(line.to_string());
+        }
+    }
+    while out.last().is_some_and(|line| line.is_empty()) {
+        out.pop();
+    }
+    out
+}
+
+fn unindent(lines: Vec<&str>) -> Vec<String> {
+    let mut lines: Vec<&str> = lines.into_iter().map(str::trim_end).collect();
+    while lines.first().is_some_and(|line| line.is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    let indent = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| margin(line))
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| dedent(line, indent).to_string())
+        .collect()
+}
+
+/// How many whitespace characters `line` starts with, counting a
+/// multibyte one like U+3000 once.
+fn margin(line: &str) -> usize {
+    line.chars().take_while(|c| c.is_whitespace()).count()
+}
+
+/// `line` without its first `indent` characters of whitespace.
+fn dedent(line: &str, indent: usize) -> &str {
+    match line.char_indices().nth(indent.min(margin(line))) {
+        Some((at, _)) => &line[at..],
+        None => "",
+    }
+}
+
+/// Drops inline markdown that reads badly in a TOML comment: code spans lose
+/// their backticks, `[text](url)` becomes `text (url)`, reference links and
+/// intra-doc links to a path keep only their text, and `*emphasis*`,
+/// `_emphasis_` and `**strong**` lose their markers. Other brackets, such as
+/// an example value `[a, b]`, and everything inside code spans stay as written.
+fn strip_markdown(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    let mut prev: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            let code = &rest[1..];
+            if let Some(end) = code.find('`') {
+                out.push_str(&code[..end]);
+                rest = &code[end + 1..];
+            } else {
+                out.push_str(code);
+                rest = "";
+            }
+            prev = Some('`');
+            continue;
+        }
+        if let Some((inner, after)) = emphasis(rest, prev) {
+            out.push_str(&strip_markdown(inner));
+            prev = inner.chars().last();
+            rest = after;
+            continue;
+        }
+        if c == '['
+            && let Some(end) = rest.find(']')
+        {
+            let text = &rest[1..end];
+            let after = &rest[end + 1..];
+            if let Some(link) = after.strip_prefix('(')
+                && let Some(close) = link.find(')')
+            {
+                out.push_str(&format!("{} ({})", strip_markdown(text), &link[..close]));
+                rest = &link[close + 1..];
+                prev = Some(')');
+                continue;
+            }
+            if let Some(reference) = after.strip_prefix('[')
+                && let Some(close) = reference.find(']')
+            {
+                out.push_str(&strip_markdown(text));
+                rest = &reference[close + 1..];
+                prev = Some(']');
+                continue;
+            }
+            if is_path(text) {
+                out.push_str(&strip_markdown(text));
+                rest = after;
+                prev = Some(']');
+                continue;
+            }
+        }
+        out.push(c);
+        prev = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// The text of an emphasis span at the start of `rest` and what follows it.
+/// As in markdown, the markers must hug the text and sit at word
+/// boundaries, so `max_connections` and `2 * 3 * 4` are left alone.
+fn emphasis(rest: &str, prev: Option<char>) -> Option<(&str, &str)> {
+    if prev.is_some_and(char::is_alphanumeric) {
+        return None;
+    }
+    let marker = ["**", "__", "*", "_"]
+        .into_iter()
+        .find(|marker| rest.starts_with(marker))?;
+    let body = &rest[marker.len()..];
+    if body.starts_with(char::is_whitespace) || body.starts_with(marker) {
+        return None;
+    }
+    let mut from = 0;
+    while let Some(at) = body[from..].find(marker) {
+        let end = from + at;
+        let after = &body[end + marker.len()..];
+        let closes = end > 0
+            && !body[..end].ends_with(char::is_whitespace)
+            && !after.starts_with(|c: char| c.is_alphanumeric() || marker.starts_with(c));
+        if closes {
+            return Some((&body[..end], after));
+        }
+        from = end + marker.len();
+    }
+    None
+}
+
+/// Whether the text of a bare `[...]` is an intra-doc link: a Rust path in
+/// backticks, or one that can't be a word, like `crate::Options` or
+/// `port()`. A plain `[server]` is more likely a TOML table.
+fn is_path(text: &str) -> bool {
+    let path = text.trim_matches('`');
+    let ticked = path.len() < text.len();
+    let (path, call) = match path.strip_suffix("()") {
+        Some(path) => (path, true),
+        None => (path, false),
+    };
+    let (path, mac) = match path.strip_suffix('!') {
+        Some(path) => (path, true),
+        None => (path, false),
+    };
+    (ticked || call || mac || path.contains("::"))
+        && path.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && path
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == ':')
+}
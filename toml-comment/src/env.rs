@@ -0,0 +1,30 @@
+use crate::{FieldInfo, RenderOptions, docs, fmt_value};
+
+pub(crate) fn render(fields: &[FieldInfo]) -> String {
+    let mut out = String::new();
+    for field in fields {
+        let Some(var) = field.env else { continue };
+        for line in docs::format(&field.expanded_docs(), &RenderOptions::default()) {
+            docs::push_comment(&mut out, "# ", &line);
+        }
+        out.push_str(&format!("{var}={}\n", fmt_env_value(field.value.as_ref())));
+    }
+    out
+}
+
+fn fmt_env_value(val: Option<&toml::Value>) -> String {
+    match val {
+        None => String::new(),
+        Some(toml::Value::String(s)) => {
+            let plain = s
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_-./:,@+".contains(c));
+            if plain {
+                s.clone()
+            } else {
+                format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+        }
+        Some(val) => fmt_value(val),
+    }
+}
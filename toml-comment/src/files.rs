@@ -0,0 +1,90 @@
+use crate::load::{LoadOptions, Loader};
+use crate::{
+    FieldInfo, FieldKind, NumberFormat, TomlComment, profiles::merge, push_docs, push_entry,
+};
+
+/// Where keys outside any section go.
+const ROOT_FILE: &str = "config.toml";
+
+pub(crate) fn split<T: TomlComment + ?Sized>(config: &T) -> Vec<(String, String)> {
+    let fields = config.fields();
+    let top_level = |field: &&FieldInfo| !field.path.contains(['.', '[']);
+
+    let opts = T::render_options();
+    let mut root = String::new();
+    config._render_docs(&mut root, &opts);
+    for field in fields
+        .iter()
+        .filter(top_level)
+        .filter(|field| field.kind == FieldKind::Value)
+    {
+        let Some(value) = &field.value else {
+            continue;
+        };
+        let mut docs = field.expanded_docs();
+        docs.extend(field.example.clone());
+        docs.extend(field.notes.iter().map(|note| note.to_string()));
+        let docs: Vec<&str> = docs.iter().map(String::as_str).collect();
+        push_docs(&mut root, &opts, None, &docs, &[]);
+        push_entry(&mut root, &field.path, value, &NumberFormat::DEFAULT, &opts);
+    }
+
+    let mut files = Vec::new();
+    if !root.is_empty() {
+        files.push((ROOT_FILE.to_string(), root));
+    }
+    files.extend(
+        fields
+            .iter()
+            .filter(top_level)
+            .filter(|field| field.kind != FieldKind::Value)
+            .filter_map(|field| {
+                let text = config.render_section(&field.path)?;
+                Some((format!("{}.toml", field.path), text))
+            }),
+    );
+    files
+}
+
+/// Parses each file and merges them in order, later files winning key by
+/// key, then deserializes the result.
+///
+/// Keys `T` has no field for are passed to serde with the rest, which
+/// skips them unless `T` has `#[serde(deny_unknown_fields)]`. Use
+/// [`load_files_with`] with
+/// [`UnknownKeys::Deny`](crate::UnknownKeys::Deny) to reject them, or
+/// [`unknown_keys`](crate::unknown_keys) to list them.
+pub fn load_files<T, I>(files: I) -> Result<T, toml::de::Error>
+where
+    T: serde::de::DeserializeOwned,
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    toml::Value::Table(merged(files, |_| {})?).try_into()
+}
+
+/// [`load_files`] with [`LoadOptions`]. Each file is matched against the
+/// config before merging.
+pub fn load_files_with<T, I>(files: I, opts: &LoadOptions) -> Result<T, toml::de::Error>
+where
+    T: TomlComment + Default + serde::de::DeserializeOwned,
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let loader = Loader::new::<T>(opts);
+    loader.finish(merged(files, |table| loader.prepare(table))?)
+}
+
+fn merged<I>(files: I, prepare: impl Fn(&mut toml::Table)) -> Result<toml::Table, toml::de::Error>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut merged = toml::Table::new();
+    for text in files {
+        let mut table = text.as_ref().parse()?;
+        prepare(&mut table);
+        merge(&mut merged, table);
+    }
+    Ok(merged)
+}
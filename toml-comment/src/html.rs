@@ -0,0 +1,63 @@
+use crate::{FieldInfo, FieldKind, fmt_value};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; max-width: 50rem; margin: 2rem auto; padding: 0 1rem; }
+.key { margin: 1.5rem 0; }
+.key > a { font-family: monospace; font-weight: bold; text-decoration: none; }
+.type { font-family: monospace; font-size: 0.85em; background: #eef; border-radius: 0.3em; padding: 0.1em 0.4em; }
+.default code { background: #f4f4f4; }
+";
+
+pub(crate) fn render(fields: &[FieldInfo]) -> String {
+    let mut out = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n");
+    out.push_str("<meta charset=\"utf-8\">\n<title>Configuration reference</title>\n");
+    out.push_str(&format!("<style>\n{STYLE}</style>\n</head>\n<body>\n"));
+    out.push_str("<h1>Configuration reference</h1>\n");
+    for field in fields {
+        let id = escape(&field.path);
+        if matches!(field.kind, FieldKind::Section | FieldKind::Tables) {
+            let header = if field.kind == FieldKind::Tables {
+                format!("[[{id}]]")
+            } else {
+                format!("[{id}]")
+            };
+            out.push_str(&format!(
+                "<h2 id=\"{id}\"><a href=\"#{id}\">{header}</a></h2>\n"
+            ));
+            push_docs(&mut out, &field.expanded_docs());
+            continue;
+        }
+        out.push_str(&format!("<div class=\"key\" id=\"{id}\">\n"));
+        out.push_str(&format!(
+            "<a href=\"#{id}\">{id}</a> <span class=\"type\">{}</span>\n",
+            escape(field.ty)
+        ));
+        push_docs(&mut out, &field.expanded_docs());
+        let default = match &field.value {
+            Some(val) => format!("<code>{}</code>", escape(&fmt_value(val))),
+            None => "unset".to_string(),
+        };
+        out.push_str(&format!(
+            "<p class=\"default\">Default: {default}</p>\n</div>\n"
+        ));
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn push_docs(out: &mut String, docs: &[String]) {
+    for paragraph in docs.split(|doc| doc.trim().is_empty()) {
+        if paragraph.is_empty() {
+            continue;
+        }
+        let text: Vec<_> = paragraph.iter().map(|doc| escape(doc.trim())).collect();
+        out.push_str(&format!("<p>{}</p>\n", text.join(" ")));
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,212 @@
+use crate::{FieldInfo, FieldKind, RenderVisitor};
+
+/// One JSON object keyed by dotted path, in render order.
+pub(crate) fn render(fields: &[FieldInfo]) -> String {
+    if fields.is_empty() {
+        return "{}\n".to_string();
+    }
+    let mut out = String::from("{\n");
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push_str(",\n");
+        }
+        out.push_str(&format!("  {}: {{\n", string(&field.path)));
+        let kind = match field.kind {
+            FieldKind::Value => "value",
+            FieldKind::Section => "section",
+            FieldKind::Tables => "tables",
+            FieldKind::Map => "map",
+        };
+        let docs: Vec<String> = field
+            .expanded_docs()
+            .iter()
+            .map(|doc| string(doc.trim()))
+            .collect();
+        let mut entries = vec![
+            ("type", string(field.ty)),
+            ("kind", string(kind)),
+            ("docs", format!("[{}]", docs.join(", "))),
+        ];
+        if let Some(value) = &field.value {
+            entries.push(("default", value_json(value)));
+        }
+        entries.push(("optional", field.optional.to_string()));
+        if let Some(env) = field.env {
+            entries.push(("env", string(env)));
+        }
+        if let Some(unit) = field.unit {
+            entries.push(("unit", string(unit)));
+        }
+        if field.secret {
+            entries.push(("secret", "true".to_string()));
+        }
+        if let Some(min) = field.min {
+            entries.push(("min", number(min)));
+        }
+        if let Some(max) = field.max {
+            entries.push(("max", number(max)));
+        }
+        if !field.choices.is_empty() {
+            let choices: Vec<String> = field.choices.iter().map(|c| string(c)).collect();
+            entries.push(("choices", format!("[{}]", choices.join(", "))));
+        }
+        let entries: Vec<String> = entries
+            .into_iter()
+            .map(|(key, val)| format!("    \"{key}\": {val}"))
+            .collect();
+        out.push_str(&entries.join(",\n"));
+        out.push_str("\n  }");
+    }
+    out.push_str("\n}\n");
+    out
+}
+
+/// JSON with `//` comments, for tools that read JSONC. Commas go in only
+/// between siblings, so plain JSON parsers accept it once comments are
+/// stripped.
+pub(crate) struct JsoncWriter {
+    pub(crate) out: String,
+    /// Per open object or array: whether it has an entry yet, and the
+    /// bracket that closes it.
+    levels: Vec<(bool, char)>,
+    /// Where the last entry ended, for the comma a following sibling adds.
+    entry_end: usize,
+}
+
+impl JsoncWriter {
+    pub(crate) fn new() -> Self {
+        Self {
+            out: "{\n".to_string(),
+            levels: vec![(false, '}')],
+            entry_end: 0,
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> String {
+        self.close();
+        self.out
+    }
+
+    fn pad(&self) -> String {
+        "  ".repeat(self.levels.len())
+    }
+
+    /// Starts an entry in the innermost object or array.
+    fn begin(&mut self) {
+        let (has_entries, _) = self.levels.last_mut().expect("open level");
+        if *has_entries {
+            self.out.insert(self.entry_end, ',');
+        }
+        *has_entries = true;
+    }
+
+    fn open(&mut self, line: String, bracket: char) {
+        self.begin();
+        self.out.push_str(&line);
+        self.out.push('\n');
+        self.levels.push((false, bracket));
+    }
+
+    fn close(&mut self) {
+        let (had_entries, bracket) = self.levels.pop().expect("open level");
+        if had_entries {
+            self.out.push_str(&format!("{}{bracket}", self.pad()));
+        } else {
+            self.out.pop();
+            self.out.push(bracket);
+        }
+        self.entry_end = self.out.len();
+        self.out.push('\n');
+    }
+}
+
+impl RenderVisitor for JsoncWriter {
+    fn on_comment(&mut self, line: &str) {
+        if line.is_empty() {
+            self.out.push_str(&format!("{}//\n", self.pad()));
+        } else {
+            self.out.push_str(&format!("{}// {line}\n", self.pad()));
+        }
+    }
+
+    fn on_key_value(&mut self, key: &str, field: &FieldInfo) {
+        self.begin();
+        let value = field
+            .value
+            .as_ref()
+            .map_or_else(|| "null".to_string(), value_json);
+        self.out
+            .push_str(&format!("{}{}: {value}", self.pad(), string(key)));
+        self.entry_end = self.out.len();
+        self.out.push('\n');
+    }
+
+    fn on_section_start(&mut self, key: &str, field: &FieldInfo) {
+        let (open, close) = if field.kind == FieldKind::Tables {
+            ('[', ']')
+        } else {
+            ('{', '}')
+        };
+        self.open(format!("{}{}: {open}", self.pad(), string(key)), close);
+    }
+
+    fn on_section_end(&mut self, _key: &str) {
+        self.close();
+    }
+
+    fn on_element_start(&mut self, _index: usize) {
+        self.open(format!("{}{{", self.pad()), '}');
+    }
+
+    fn on_element_end(&mut self, _index: usize) {
+        self.close();
+    }
+}
+
+fn value_json(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => string(s),
+        toml::Value::Integer(n) => n.to_string(),
+        toml::Value::Float(f) => number(*f),
+        toml::Value::Boolean(b) => b.to_string(),
+        toml::Value::Datetime(dt) => string(&dt.to_string()),
+        toml::Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(value_json).collect();
+            format!("[{}]", items.join(", "))
+        }
+        toml::Value::Table(table) => {
+            let entries: Vec<String> = table
+                .iter()
+                .map(|(k, v)| format!("{}: {}", string(k), value_json(v)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+    }
+}
+
+/// JSON has no `inf`/`nan`, so those become `null`.
+fn number(f: f64) -> String {
+    if f.is_finite() {
+        format!("{f:?}")
+    } else {
+        "null".to_string()
+    }
+}
+
+pub(crate) fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -1,32 +1,1759 @@
-pub use toml_comment_derive::TomlComment;
+pub mod bytesize;
+mod configmap;
+mod diff;
+mod docs;
+mod env;
+mod files;
+mod html;
+mod json;
+mod lines;
+mod load;
+mod man;
+mod managed;
+mod map_root;
+mod profiles;
+mod visit;
+mod yaml;
 
-pub trait TomlComment: serde::Serialize + Default {
-    fn default_toml() -> String;
+pub use diff::{ConfigDiff, KeyChange, diff_fields};
+pub use files::{load_files, load_files_with};
+pub use lines::{LineLog, RenderedLine};
+pub use load::{Aliases, LoadOptions, UnknownKeys, fold_keys, unknown_keys};
+pub use managed::{ManagedBlock, check_managed};
+pub use profiles::Profiles;
+pub use toml_comment_derive::{TomlComment, TypeDocs};
+pub use visit::RenderVisitor;
+
+use std::cell::Cell;
+use std::fmt::Write as _;
+
+/// Exposes a type's own `///` docs so fields marked
+/// `#[toml_comment(type_docs)]` can fall back to them. Derive it on newtypes
+/// and enums used as leaf fields.
+pub trait TypeDocs {
+    const DOCS: &'static [&'static str];
+
+    /// For `#[serde(untagged)]` enums, one line per accepted form: the
+    /// variant's first doc line, or the type it wraps.
+    const FORMS: &'static [&'static str] = &[];
+
+    /// For enums of unit variants, the names they serialize as.
+    const CHOICES: &'static [&'static str] = &[];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Value,
+    Section,
+    /// A `Vec` of structs rendered as `[[array of tables]]`. Each element's
+    /// fields follow with an indexed path such as `upstream[0].host`.
+    Tables,
+    Map,
+}
+
+/// A single field as seen by the derive: its dotted key path, the Rust type
+/// as written, its doc comment lines and its current value. Sections carry no
+/// value; their own fields follow them in the list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldInfo {
+    pub path: String,
+    pub ty: &'static str,
+    pub kind: FieldKind,
+    pub docs: &'static [&'static str],
+    pub value: Option<toml::Value>,
+    pub env: Option<&'static str>,
+    /// Whether the key may be left out of the file: an `Option` or a
+    /// `#[serde(default)]` field.
+    pub optional: bool,
+    /// The unit from `#[toml_comment(unit = "...")]`.
+    pub unit: Option<&'static str>,
+    /// Marked `#[toml_comment(secret)]`: a password or token that editors
+    /// should mask.
+    pub secret: bool,
+    /// Bounds from `#[toml_comment(min = ..., max = ...)]`.
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// The values an enum field accepts, with `#[toml_comment(choices)]`.
+    pub choices: &'static [&'static str],
+    /// The `e.g. ...` line from `#[toml_comment(example = ...)]`.
+    pub example: Option<String>,
+    /// The fixed lines rendered after the docs: `unit: ...`, `see: ...`,
+    /// `requires: ...` and so on.
+    pub notes: &'static [&'static str],
+    /// Other names serde accepts for the key, from `#[serde(alias)]`.
+    pub aliases: &'static [&'static str],
+    /// Keys from `#[toml_comment(requires = ...)]` and `conflicts_with`,
+    /// relative to the table this key lives in.
+    pub requires: &'static [&'static str],
+    pub conflicts_with: &'static [&'static str],
+}
+
+impl FieldInfo {
+    /// The dotted path of the table this key lives in, `""` at the top
+    /// level. Settings editors can group fields by it.
+    pub fn section(&self) -> &str {
+        match self.path.rfind('.') {
+            Some(dot) => &self.path[..dot],
+            None => "",
+        }
+    }
+
+    /// The doc lines with `{type}`, `{env}` and `{default}` filled in from
+    /// this field's metadata.
+    pub fn expanded_docs(&self) -> Vec<String> {
+        let default = self
+            .value
+            .as_ref()
+            .map_or_else(|| "unset".to_string(), fmt_value);
+        let vars = [
+            ("type", self.ty),
+            ("env", self.env.unwrap_or_default()),
+            ("default", default.as_str()),
+        ];
+        self.docs.iter().map(|doc| expand(doc, &vars)).collect()
+    }
+}
+
+/// A leaf key of a config, as listed by [`TomlComment::key_paths`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPath {
+    pub path: String,
+    pub ty: &'static str,
+    pub optional: bool,
+}
+
+pub type Localize = dyn Fn(&str) -> Option<String>;
+
+/// Knobs for [`TomlComment::to_commented_toml_with`]. The default renders
+/// exactly what [`TomlComment::to_commented_toml`] does.
+#[derive(Default)]
+pub struct RenderOptions<'a> {
+    /// Resolves `#[toml_comment(i18n_key = "...")]` keys to translated
+    /// comments. Returning `None` falls back to the doc comment.
+    pub localize: Option<&'a Localize>,
+    /// Strips inline markdown (code spans, links, emphasis) from comments.
+    pub strip_markdown: bool,
+    /// Leaves `#[doc(hidden)]` fields out entirely instead of rendering
+    /// them without comments.
+    pub skip_hidden: bool,
+    /// Deepest table nesting to render; `a.b.c` is depth 3. Going deeper
+    /// panics, which turns a runaway recursive config into a clear error
+    /// instead of a stack overflow. `None` renders any depth.
+    pub max_depth: Option<usize>,
+    /// Adds an `# optional` line to the comment of `Option` fields that
+    /// render.
+    pub mark_optional: bool,
+    /// Renders `None` leaf fields as a commented `# key = <string>` line
+    /// under their docs instead of leaving them out.
+    pub show_unset: bool,
+    /// Adds a `# type: integer` (`string`, `array of strings`, ...) line to
+    /// each value's comment.
+    pub type_hints: bool,
+    /// Adds a `# one of: "light", "dark"` line to the comment of fields
+    /// marked `#[toml_comment(choices)]`.
+    pub list_choices: bool,
+    /// Adds `# default: ...`, `# env: ...` and `# range: ...` lines to each
+    /// value's comment, from its `Default` impl and attributes.
+    pub annotate: bool,
+    /// Writes floats of a million or more, or below 0.0001, in scientific
+    /// notation (`1.5e9`) everywhere, as `#[toml_comment(scientific)]` does
+    /// per field.
+    pub scientific_floats: bool,
+    /// Indents the keys and comments under each `[section]` header by this
+    /// many spaces. Headers stay at the margin.
+    pub indent: usize,
+    /// What comment lines start with; `None` is `"# "`.
+    pub comment_prefix: Option<&'a str>,
+    /// The prefix for a struct's or enum's own docs, e.g. `"## "` to set
+    /// them apart from field comments. `None` uses `comment_prefix`.
+    pub type_comment_prefix: Option<&'a str>,
+    /// Boxes each top-level section header in a banner this many columns
+    /// wide (`# == Server ====`).
+    pub banner_width: Option<usize>,
+    /// Blank lines before a section that follows other content; `None` is
+    /// one. A file's first section only gets them when the struct has docs
+    /// above it.
+    pub section_gap: Option<usize>,
+    /// Puts a blank line between the struct's own docs and its first key.
+    pub blank_after_docs: bool,
+    /// Writes arrays and strings the way `toml::to_string_pretty` does
+    /// (arrays of two or more elements one per line, `"""` strings for
+    /// multi-line text), to match files produced by it. Inline tables and
+    /// fields with a number format keep this crate's style.
+    pub toml_pretty: bool,
+    /// Widest `key = value` line before long arrays go one element per line
+    /// and long strings wrap; `None` is 80.
+    pub line_width: Option<usize>,
+    /// Spaces before each element of an array too long for one line; `None`
+    /// is four.
+    pub array_indent: Option<usize>,
+    /// Prepended to the keys of the table being rendered, for
+    /// `#[toml_comment(flatten, prefix = "...")]`.
+    #[doc(hidden)]
+    pub key_prefix: &'a str,
+    /// Set while writing the inside of a `[table]`, where keys and comments
+    /// get `indent`. Cleared for the comments above a header.
+    #[doc(hidden)]
+    pub in_table: bool,
+    /// Where rendering records the first [`RenderError`], for
+    /// [`TomlComment::try_to_commented_toml_with`]. Without it, rendering
+    /// panics with the error.
+    #[doc(hidden)]
+    pub error: Option<&'a Cell<Option<RenderError>>>,
+    /// Notes what each written line is, for [`TomlComment::lines`].
+    #[doc(hidden)]
+    pub line_log: Option<&'a LineLog>,
+}
+
+/// Why [`TomlComment::try_to_commented_toml_with`] couldn't render a
+/// config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RenderError {
+    /// A table nested deeper than `RenderOptions::max_depth`, such as a
+    /// runaway recursive config.
+    TooDeep {
+        /// The table's dotted path.
+        table: String,
+        depth: usize,
+        max_depth: usize,
+    },
+    /// A map key with no TOML form, such as a tuple or a struct.
+    MapKey {
+        /// The key as TOML, or why it has no TOML form.
+        key: String,
+    },
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::TooDeep {
+                table,
+                depth,
+                max_depth,
+            } => write!(
+                f,
+                "TomlComment: [{table}] is nested {depth} tables deep, past max_depth of {max_depth}"
+            ),
+            RenderError::MapKey { key } => write!(
+                f,
+                "TomlComment: map key {key} is not a string, integer, boolean or unit enum variant"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// A relation from `#[toml_comment(requires)]` or `conflicts_with` that a
+/// config breaks, as found by [`TomlComment::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// `key` is set but `requires` isn't.
+    Requires { key: String, requires: String },
+    /// `key` and `conflicts_with` are both set.
+    ConflictsWith { key: String, conflicts_with: String },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Requires { key, requires } => write!(f, "`{key}` requires `{requires}`"),
+            Violation::ConflictsWith {
+                key,
+                conflicts_with,
+            } => write!(f, "`{key}` conflicts with `{conflicts_with}`"),
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
+
+impl RenderOptions<'_> {
+    /// Output laid out the way `taplo fmt`'s documented defaults ask for:
+    /// two-space array elements, and keys at the margin. Nothing here runs
+    /// taplo, so check your own files with it.
+    pub fn taplo() -> Self {
+        Self {
+            array_indent: Some(2),
+            ..Self::default()
+        }
+    }
+
+    /// As short as the file gets with its comments: no blank lines between
+    /// tables, and markdown reduced to plain text.
+    pub fn compact() -> Self {
+        Self {
+            section_gap: Some(0),
+            strip_markdown: true,
+            ..Self::default()
+        }
+    }
+
+    /// Notes what the docs don't say: which keys are optional, which
+    /// values an enum accepts, and unset options as commented-out lines.
+    pub fn documented() -> Self {
+        Self {
+            mark_optional: true,
+            show_unset: true,
+            list_choices: true,
+            blank_after_docs: true,
+            ..Self::default()
+        }
+    }
+
+    /// [`documented`](Self::documented) plus type, default, env and range
+    /// lines for every value.
+    pub fn verbose() -> Self {
+        Self {
+            type_hints: true,
+            annotate: true,
+            ..Self::documented()
+        }
+    }
+
+    /// A sample file for users to copy from: unset options appear
+    /// commented out, and `#[doc(hidden)]` fields are left out.
+    pub fn example() -> Self {
+        Self {
+            show_unset: true,
+            skip_hidden: true,
+            ..Self::default()
+        }
+    }
+}
+
+pub trait TomlComment: serde::Serialize {
+    /// The options `to_commented_toml()` renders with; the defaults unless
+    /// the type sets `#[toml_comment(style(...))]`.
+    fn render_options() -> RenderOptions<'static> {
+        RenderOptions::default()
+    }
+
+    fn default_toml() -> String
+    where
+        Self: Default,
+    {
+        Self::default().to_commented_toml()
+    }
+
+    fn to_commented_toml(&self) -> String {
+        self.to_commented_toml_with(&Self::render_options())
+    }
+
+    /// Panics where [`try_to_commented_toml_with`](Self::try_to_commented_toml_with)
+    /// returns an error.
+    fn to_commented_toml_with(&self, opts: &RenderOptions) -> String {
+        self.try_to_commented_toml_with(opts)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Renders with `opts`, or says why it can't: a table nested past
+    /// `max_depth`, or a map key TOML can't write.
+    fn try_to_commented_toml_with(&self, opts: &RenderOptions) -> Result<String, RenderError> {
+        let error = Cell::new(None);
+        let opts = &RenderOptions {
+            error: Some(&error),
+            ..*opts
+        };
+        let mut out = String::with_capacity(self._size_hint());
+        self._render_docs(&mut out, opts);
+        if opts.blank_after_docs && !out.is_empty() {
+            out.push('\n');
+        }
+        self._render(&mut out, TablePath::ROOT, opts);
+        match error.into_inner() {
+            Some(err) => Err(err),
+            None => Ok(out),
+        }
+    }
+
+    fn to_commented_toml_localized(&self, localize: &Localize) -> String {
+        self.to_commented_toml_with(&RenderOptions {
+            localize: Some(localize),
+            ..Self::render_options()
+        })
+    }
+
+    /// One table by dotted path (`"database.pool"`), with its comments and
+    /// everything nested in it, as `to_commented_toml()` writes it. `None`
+    /// when the path doesn't name a `[section]`, `[[table]]` array or map
+    /// table.
+    fn render_section(&self, path: &str) -> Option<String> {
+        let opts = Self::render_options();
+        let mut out = String::new();
+        if !self._render_section(&mut out, TablePath::ROOT, path, &opts) {
+            return None;
+        }
+        Some(out.trim_start_matches('\n').to_string())
+    }
+
+    /// `to_commented_toml()` one line at a time, sorted into comments,
+    /// entries and headers, for pagers and TUIs that style each kind.
+    fn lines(&self) -> impl Iterator<Item = RenderedLine> {
+        let log = LineLog::default();
+        let opts = RenderOptions {
+            indent: 0,
+            line_log: Some(&log),
+            ..Self::render_options()
+        };
+        let toml = self.to_commented_toml_with(&opts);
+        log.split(&toml).into_iter()
+    }
+
+    fn fields(&self) -> Vec<FieldInfo> {
+        let mut out = Vec::new();
+        self._fields(&mut out, "");
+        out
+    }
+
+    /// Every key that holds a value (not a `[section]` or `[[table]]`
+    /// header) in the default config, with its Rust type. Arrays of tables
+    /// contribute the keys of the elements they hold by default.
+    fn key_paths() -> impl Iterator<Item = KeyPath>
+    where
+        Self: Default,
+    {
+        Self::default()
+            .fields()
+            .into_iter()
+            .filter(|field| matches!(field.kind, FieldKind::Value | FieldKind::Map))
+            .map(|field| KeyPath {
+                path: field.path,
+                ty: field.ty,
+                optional: field.optional,
+            })
+    }
+
+    /// Checks the `requires` and `conflicts_with` relations between keys.
+    /// A key counts as set when it holds anything but `false`, `""` or an
+    /// empty array or table.
+    fn validate(&self) -> Result<(), Vec<Violation>> {
+        let fields = self.fields();
+        let is_set = |path: &str| {
+            fields
+                .iter()
+                .find(|field| field.path == path)
+                .and_then(|field| field.value.as_ref())
+                .is_some_and(|value| match value {
+                    toml::Value::Boolean(set) => *set,
+                    toml::Value::String(s) => !s.is_empty(),
+                    toml::Value::Array(items) => !items.is_empty(),
+                    toml::Value::Table(table) => !table.is_empty(),
+                    _ => true,
+                })
+        };
+        let mut violations = Vec::new();
+        for field in fields.iter().filter(|field| is_set(&field.path)) {
+            let relative = |key: &str| match field.section() {
+                "" => key.to_string(),
+                section => format!("{section}.{key}"),
+            };
+            for other in field.requires.iter().map(|key| relative(key)) {
+                if !is_set(&other) {
+                    violations.push(Violation::Requires {
+                        key: field.path.clone(),
+                        requires: other,
+                    });
+                }
+            }
+            for other in field.conflicts_with.iter().map(|key| relative(key)) {
+                if is_set(&other) {
+                    violations.push(Violation::ConflictsWith {
+                        key: field.path.clone(),
+                        conflicts_with: other,
+                    });
+                }
+            }
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// For an enum: one `[section]` block per variant, each built from its
+    /// fields' defaults, so users can see every shape the table can take.
+    /// All but the `Default` variant (the first when there's no `Default`)
+    /// are commented out. Variants with a field that has no `Default` are
+    /// left out; for structs this is empty.
+    fn variant_samples(section: &str) -> String
+    where
+        Self: Sized,
+    {
+        let opts = Self::render_options();
+        let (samples, default) = Self::_variant_samples();
+        let active = default.unwrap_or(0);
+        let mut out = String::new();
+        for (i, sample) in samples.iter().enumerate() {
+            if i > 0 {
+                push_gap(&mut out, &opts);
+            }
+            let mut block = String::new();
+            if let Some((variant, docs)) = sample._unit_variant() {
+                sample._render_docs(&mut block, &opts);
+                push_docs(&mut block, &opts, None, docs, &[]);
+                let value = toml::Value::String(variant.to_string());
+                push_entry(&mut block, section, &value, &NumberFormat::DEFAULT, &opts);
+            } else {
+                let _ = writeln!(block, "[{section}]");
+                let table_opts = RenderOptions {
+                    in_table: true,
+                    ..opts
+                };
+                sample._render_docs(&mut block, &table_opts);
+                sample._render(&mut block, TablePath::ROOT.join(section), &table_opts);
+            }
+            if i == active {
+                out.push_str(&block);
+                continue;
+            }
+            for line in block.lines() {
+                docs::push_comment(&mut out, "# ", line);
+            }
+        }
+        out
+    }
+
+    /// Walks the config's keys, sections and doc comments in render order,
+    /// for writing other formats from the same derive.
+    fn visit(&self, visitor: &mut dyn RenderVisitor) {
+        visit::walk(&self.fields(), visitor);
+    }
+
+    /// The same keys and comments as block-style YAML.
+    fn to_commented_yaml(&self) -> String {
+        let mut writer = yaml::YamlWriter::default();
+        self.visit(&mut writer);
+        writer.out
+    }
+
+    /// The same keys and comments as JSON with `//` comments.
+    fn to_commented_jsonc(&self) -> String {
+        let mut writer = json::JsoncWriter::new();
+        self.visit(&mut writer);
+        writer.finish()
+    }
+
+    fn to_env_example(&self) -> String {
+        env::render(&self.fields())
+    }
+
+    fn to_man_page(&self) -> String {
+        man::render(&self.fields())
+    }
+
+    fn to_html_docs(&self) -> String {
+        html::render(&self.fields())
+    }
+
+    /// The metadata from [`fields`](Self::fields) as a JSON object keyed by
+    /// dotted path, for tooling that can't link against the binary.
+    fn schema_json(&self) -> String {
+        json::render(&self.fields())
+    }
+
+    /// One `(file name, contents)` pair per top-level section, e.g.
+    /// `("database.toml", ...)`, each written as
+    /// [`render_section`](Self::render_section) would, for `conf.d`-style
+    /// layouts. Top-level keys go first, in `config.toml`. Read the files
+    /// back with [`load_files`].
+    fn to_commented_files(&self) -> Vec<(String, String)> {
+        files::split(self)
+    }
+
+    fn to_config_map(&self, name: &str, file_name: &str) -> String {
+        configmap::render(name, file_name, &self.to_commented_toml())
+    }
+
+    /// The commented TOML between `# BEGIN managed by {owner} (sha256:...)`
+    /// and `# END managed` markers. [`check_managed`] tells whether the block
+    /// was edited since.
+    fn to_managed_toml(&self, owner: &str) -> String {
+        managed::wrap(owner, &self.to_commented_toml())
+    }
+
+    /// Replaces the managed block in `existing` with freshly rendered
+    /// defaults, leaving the rest of the file as it was. A file without a
+    /// block comes back unchanged; write the first one with
+    /// [`to_managed_toml`](Self::to_managed_toml).
+    fn regenerate_managed(existing: &str) -> String
+    where
+        Self: Default,
+    {
+        managed::replace(existing, &Self::default_toml()).unwrap_or_else(|| existing.to_string())
+    }
+
+    #[doc(hidden)]
+    fn _render_docs(&self, out: &mut String, opts: &RenderOptions);
+
+    #[doc(hidden)]
+    fn _render(&self, out: &mut String, prefix: TablePath, opts: &RenderOptions);
+
+    #[doc(hidden)]
+    fn _fields(&self, out: &mut Vec<FieldInfo>, prefix: &str);
+
+    #[doc(hidden)]
+    fn _render_section(
+        &self,
+        _out: &mut String,
+        _prefix: TablePath,
+        _path: &str,
+        _opts: &RenderOptions,
+    ) -> bool {
+        false
+    }
+
+    /// Every variant of an enum that can be built from defaults, and which
+    /// of them `Self::default()` is.
+    #[doc(hidden)]
+    fn _variant_samples() -> (Vec<Self>, Option<usize>)
+    where
+        Self: Sized,
+    {
+        (Vec::new(), None)
+    }
+
+    /// The type's name as a field of it would spell it, for
+    /// [`FieldInfo::ty`] where no field names it, as in a map-root config.
+    #[doc(hidden)]
+    fn _type_name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// The name and doc lines of the unit variant an externally tagged
+    /// enum holds, which serde writes as a plain `key = "Variant"`.
+    #[doc(hidden)]
+    fn _unit_variant(&self) -> Option<(&'static str, &'static [&'static str])> {
+        None
+    }
+
+    /// Roughly how many bytes `to_commented_toml()` writes, so the output
+    /// is allocated once. 0 when unknown.
+    #[doc(hidden)]
+    fn _size_hint(&self) -> usize {
+        0
+    }
+}
+
+/// References render as the config they point to, so functions taking
+/// `impl TomlComment` accept `&config` without a clone.
+impl<T: TomlComment + ?Sized> TomlComment for &T {
+    fn render_options() -> RenderOptions<'static> {
+        T::render_options()
+    }
+
+    fn _type_name() -> &'static str {
+        T::_type_name()
+    }
+
+    fn _unit_variant(&self) -> Option<(&'static str, &'static [&'static str])> {
+        (**self)._unit_variant()
+    }
+
+    fn _render_docs(&self, out: &mut String, opts: &RenderOptions) {
+        (**self)._render_docs(out, opts);
+    }
+
+    fn _render(&self, out: &mut String, prefix: TablePath, opts: &RenderOptions) {
+        (**self)._render(out, prefix, opts);
+    }
+
+    fn _fields(&self, out: &mut Vec<FieldInfo>, prefix: &str) {
+        (**self)._fields(out, prefix);
+    }
+
+    fn _render_section(
+        &self,
+        out: &mut String,
+        prefix: TablePath,
+        path: &str,
+        opts: &RenderOptions,
+    ) -> bool {
+        (**self)._render_section(out, prefix, path, opts)
+    }
+
+    fn _size_hint(&self) -> usize {
+        (**self)._size_hint()
+    }
+}
+
+impl<T: TomlComment + ?Sized> TomlComment for Box<T> {
+    fn render_options() -> RenderOptions<'static> {
+        T::render_options()
+    }
+
+    fn _type_name() -> &'static str {
+        T::_type_name()
+    }
+
+    fn _unit_variant(&self) -> Option<(&'static str, &'static [&'static str])> {
+        (**self)._unit_variant()
+    }
+
+    fn _render_docs(&self, out: &mut String, opts: &RenderOptions) {
+        (**self)._render_docs(out, opts);
+    }
+
+    fn _render(&self, out: &mut String, prefix: TablePath, opts: &RenderOptions) {
+        (**self)._render(out, prefix, opts);
+    }
+
+    fn _fields(&self, out: &mut Vec<FieldInfo>, prefix: &str) {
+        (**self)._fields(out, prefix);
+    }
+
+    fn _render_section(
+        &self,
+        out: &mut String,
+        prefix: TablePath,
+        path: &str,
+        opts: &RenderOptions,
+    ) -> bool {
+        (**self)._render_section(out, prefix, path, opts)
+    }
+
+    fn _size_hint(&self) -> usize {
+        (**self)._size_hint()
+    }
+}
+
+/// The rendering half of [`TomlComment`], usable as a trait object:
+/// `Vec<Box<dyn TomlCommentRender>>` can hold differently typed plugin
+/// configs and render them the same way. Every `TomlComment` type
+/// implements it. Import it only where you render trait objects; with both
+/// traits in scope, call the concrete type's methods as
+/// `TomlComment::to_commented_toml(&config)`.
+pub trait TomlCommentRender {
     fn to_commented_toml(&self) -> String;
 
+    fn to_commented_toml_with(&self, opts: &RenderOptions) -> String;
+
+    fn try_to_commented_toml_with(&self, opts: &RenderOptions) -> Result<String, RenderError>;
+
     #[doc(hidden)]
-    fn _render(&self, out: &mut String, prefix: &str);
+    fn _render(&self, out: &mut String, prefix: TablePath, opts: &RenderOptions);
+}
+
+impl<T: TomlComment> TomlCommentRender for T {
+    fn to_commented_toml(&self) -> String {
+        TomlComment::to_commented_toml(self)
+    }
+
+    fn to_commented_toml_with(&self, opts: &RenderOptions) -> String {
+        TomlComment::to_commented_toml_with(self, opts)
+    }
+
+    fn try_to_commented_toml_with(&self, opts: &RenderOptions) -> Result<String, RenderError> {
+        TomlComment::try_to_commented_toml_with(self, opts)
+    }
+
+    fn _render(&self, out: &mut String, prefix: TablePath, opts: &RenderOptions) {
+        TomlComment::_render(self, out, prefix, opts);
+    }
+}
+
+/// Boxed and borrowed trait objects render as the config they hold, so a
+/// `Box<dyn TomlCommentRender>` goes wherever an `impl TomlCommentRender`
+/// is taken.
+impl TomlCommentRender for Box<dyn TomlCommentRender + '_> {
+    fn to_commented_toml(&self) -> String {
+        (**self).to_commented_toml()
+    }
+
+    fn to_commented_toml_with(&self, opts: &RenderOptions) -> String {
+        (**self).to_commented_toml_with(opts)
+    }
+
+    fn try_to_commented_toml_with(&self, opts: &RenderOptions) -> Result<String, RenderError> {
+        (**self).try_to_commented_toml_with(opts)
+    }
+
+    fn _render(&self, out: &mut String, prefix: TablePath, opts: &RenderOptions) {
+        (**self)._render(out, prefix, opts);
+    }
+}
+
+impl TomlCommentRender for &dyn TomlCommentRender {
+    fn to_commented_toml(&self) -> String {
+        (**self).to_commented_toml()
+    }
+
+    fn to_commented_toml_with(&self, opts: &RenderOptions) -> String {
+        (**self).to_commented_toml_with(opts)
+    }
+
+    fn try_to_commented_toml_with(&self, opts: &RenderOptions) -> Result<String, RenderError> {
+        (**self).try_to_commented_toml_with(opts)
+    }
+
+    fn _render(&self, out: &mut String, prefix: TablePath, opts: &RenderOptions) {
+        (**self)._render(out, prefix, opts);
+    }
+}
+
+#[doc(hidden)]
+pub fn push_docs(
+    out: &mut String,
+    opts: &RenderOptions,
+    key: Option<&str>,
+    docs: &[&str],
+    vars: &[(&str, &str)],
+) {
+    let prefix = opts.comment_prefix.unwrap_or("# ");
+    push_docs_with(out, opts, prefix, key, docs, vars);
+}
+
+#[doc(hidden)]
+pub fn push_type_docs(out: &mut String, opts: &RenderOptions, key: Option<&str>, docs: &[&str]) {
+    let prefix = opts
+        .type_comment_prefix
+        .or(opts.comment_prefix)
+        .unwrap_or("# ");
+    push_docs_with(out, opts, prefix, key, docs, &[]);
+}
+
+fn push_docs_with(
+    out: &mut String,
+    opts: &RenderOptions,
+    prefix: &str,
+    key: Option<&str>,
+    docs: &[&str],
+    vars: &[(&str, &str)],
+) {
+    let localized = key
+        .zip(opts.localize)
+        .and_then(|(key, localize)| localize(key));
+    let lines: Vec<String> = match &localized {
+        Some(text) => vec![expand(text, vars)],
+        None => docs.iter().map(|doc| expand(doc, vars)).collect(),
+    };
+    let prefix = format!("{}{prefix}", " ".repeat(pad(opts)));
+    for line in docs::format(&lines, opts) {
+        let start = out.len();
+        docs::push_comment(out, &prefix, &line);
+        log_comment(out, opts, start);
+    }
+}
+
+#[doc(hidden)]
+pub fn push_forms(out: &mut String, opts: &RenderOptions, forms: &[&str]) {
+    if forms.is_empty() {
+        return;
+    }
+    let mut lines = vec!["Accepts one of:".to_string()];
+    lines.extend(forms.iter().map(|form| format!("- {form}")));
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    push_docs(out, opts, None, &lines, &[]);
+}
+
+/// Writes the `banner_width` box above a top-level section: the name in
+/// sentence case between `=` rules.
+#[doc(hidden)]
+pub fn push_banner(out: &mut String, opts: &RenderOptions, prefix: TablePath, name: &str) {
+    let Some(width) = opts.banner_width else {
+        return;
+    };
+    if !prefix.is_empty() {
+        return;
+    }
+    let mut title: String = name
+        .chars()
+        .map(|c| if c == '_' || c == '-' { ' ' } else { c })
+        .collect();
+    if let Some(first) = title.get(..1) {
+        title = first.to_uppercase() + &title[1..];
+    }
+    let rule = format!("# {}", "=".repeat(width.saturating_sub(2)));
+    let label = format!("# == {title} ");
+    let fill = width.saturating_sub(label.chars().count()).max(2);
+    for line in [&rule, &format!("{label}{}", "=".repeat(fill)), &rule] {
+        let start = out.len();
+        let _ = writeln!(out, "{line}");
+        log_comment(out, opts, start);
+    }
+}
+
+/// Notes the comment line written from `start` in `opts.line_log`.
+fn log_comment(out: &str, opts: &RenderOptions, start: usize) {
+    if let Some(log) = opts.line_log {
+        log.comment(start, out.len() - 1);
+    }
+}
+
+/// Writes a `[section]` header, or `[[section]]` for an array of tables.
+#[doc(hidden)]
+pub fn push_header(
+    out: &mut String,
+    opts: &RenderOptions,
+    section: impl std::fmt::Display,
+    array: bool,
+) {
+    let start = out.len();
+    let _ = if array {
+        writeln!(out, "[[{section}]]")
+    } else {
+        writeln!(out, "[{section}]")
+    };
+    if let Some(log) = opts.line_log {
+        log.header(start, out.len() - 1, array);
+    }
+}
+
+/// Writes the commented-out header standing in for an absent table.
+#[doc(hidden)]
+pub fn push_absent_header(
+    out: &mut String,
+    opts: &RenderOptions,
+    section: impl std::fmt::Display,
+    array: bool,
+) {
+    let start = out.len();
+    let _ = if array {
+        writeln!(out, "# [[{section}]]")
+    } else {
+        writeln!(out, "# [{section}]")
+    };
+    log_comment(out, opts, start);
+}
+
+/// Writes an unset key as a commented `# key = <placeholder>` line.
+#[doc(hidden)]
+pub fn push_unset(out: &mut String, opts: &RenderOptions, key: &str, placeholder: &str) {
+    let start = out.len();
+    push_indent(out, opts);
+    let _ = writeln!(out, "# {key} = {placeholder}");
+    log_comment(out, opts, start);
+}
+
+/// Spaces before a key or comment line: `indent` inside a table, none at
+/// the top level.
+fn pad(opts: &RenderOptions) -> usize {
+    if opts.in_table { opts.indent } else { 0 }
+}
+
+/// Writes [`pad`] for a line the derive writes itself, such as an unset
+/// key's `# key = <string>`.
+#[doc(hidden)]
+pub fn push_indent(out: &mut String, opts: &RenderOptions) {
+    out.extend(std::iter::repeat_n(' ', pad(opts)));
+}
+
+/// Ends `out` with `section_gap` blank lines, counting any already there.
+#[doc(hidden)]
+pub fn push_gap(out: &mut String, opts: &RenderOptions) {
+    let lines = opts.section_gap.unwrap_or(1);
+    let trailing = out.len() - out.trim_end_matches('\n').len();
+    for _ in trailing.saturating_sub(1)..lines {
+        out.push('\n');
+    }
+}
+
+/// Whether `section` is within `max_depth`. When it isn't, the error is
+/// reported and the caller stops descending.
+#[doc(hidden)]
+#[must_use]
+pub fn check_depth(opts: &RenderOptions, section: TablePath) -> bool {
+    let Some(max_depth) = opts.max_depth else {
+        return true;
+    };
+    let depth = section.depth();
+    if depth <= max_depth {
+        return true;
+    }
+    report(
+        opts,
+        RenderError::TooDeep {
+            table: section.to_string(),
+            depth,
+            max_depth,
+        },
+    );
+    false
+}
+
+/// Records `err` in `opts.error` unless an earlier error is there, or
+/// panics with it when there's nowhere to record it.
+fn report(opts: &RenderOptions, err: RenderError) {
+    match opts.error {
+        Some(cell) => {
+            let first = cell.take().unwrap_or(err);
+            cell.set(Some(first));
+        }
+        None => panic!("{err}"),
+    }
+}
+
+/// The dotted path of the table being rendered, kept as a chain of
+/// borrowed keys on the stack so descending into a table doesn't allocate
+/// its name. `Display` writes it as it goes in a header.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+pub struct TablePath<'a> {
+    parent: Option<&'a TablePath<'a>>,
+    key: &'a str,
+}
+
+impl<'a> TablePath<'a> {
+    pub const ROOT: TablePath<'static> = TablePath {
+        parent: None,
+        key: "",
+    };
+
+    pub fn join(&'a self, key: &'a str) -> TablePath<'a> {
+        TablePath {
+            parent: Some(self).filter(|parent| !parent.is_empty()),
+            key,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_none() && self.key.is_empty()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.parent.map_or(1, |parent| parent.depth() + 1)
+    }
+}
+
+impl std::fmt::Display for TablePath<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(parent) = self.parent {
+            write!(f, "{parent}.")?;
+        }
+        f.write_str(self.key)
+    }
+}
+
+/// A key with the `flatten(prefix = "...")` prefix in front, borrowed
+/// as-is when there's none.
+#[doc(hidden)]
+pub fn prefixed_key<'a>(prefix: &str, key: &'a str) -> std::borrow::Cow<'a, str> {
+    if prefix.is_empty() {
+        std::borrow::Cow::Borrowed(key)
+    } else {
+        std::borrow::Cow::Owned(format!("{prefix}{key}"))
+    }
+}
+
+#[doc(hidden)]
+pub fn fmt_default<T: serde::Serialize>(val: &T) -> String {
+    toml::Value::try_from(val).map_or_else(|_| "unset".to_string(), |val| fmt_value(&val))
+}
+
+fn expand(line: &str, vars: &[(&str, &str)]) -> String {
+    let mut line = line.to_string();
+    for (name, value) in vars {
+        line = line.replace(&format!("{{{name}}}"), value);
+    }
+    line
+}
+
+/// Converts map entries to TOML key/value pairs. Keys may be strings,
+/// integers, booleans or unit enum variants; integer keys render as their
+/// decimal text (`8080 = ...`), which TOML reads back as the string "8080".
+/// Entries whose value has no TOML form (`None`) are skipped, like `toml`
+/// does, and so are those whose key has none, after reporting it as a
+/// [`RenderError::MapKey`]. `sort` orders keys for maps without a stable
+/// iteration order.
+#[doc(hidden)]
+pub fn map_entries<'a, K, V>(
+    map: impl IntoIterator<Item = (&'a K, &'a V)>,
+    sort: bool,
+    opts: &RenderOptions,
+) -> Vec<(String, toml::Value)>
+where
+    K: serde::Serialize + 'a,
+    V: serde::Serialize + 'a,
+{
+    let mut entries = Vec::new();
+    for_each_map_entry(map, sort, opts, |k, v| entries.push((k, v)));
+    entries
+}
+
+/// [`map_entries`] one entry at a time: each value is serialized just
+/// before `f` gets it, and nothing is collected unless `sort` needs the
+/// keys up front.
+#[doc(hidden)]
+pub fn for_each_map_entry<'a, K, V>(
+    map: impl IntoIterator<Item = (&'a K, &'a V)>,
+    sort: bool,
+    opts: &RenderOptions,
+    mut f: impl FnMut(String, toml::Value),
+) where
+    K: serde::Serialize + 'a,
+    V: serde::Serialize + 'a,
+{
+    let mut emit = |k: String, v: &V| {
+        if let Ok(v) = toml::Value::try_from(v) {
+            f(k, v);
+        }
+    };
+    if sort {
+        let mut keyed: Vec<(String, &V)> = map
+            .into_iter()
+            .filter_map(|(k, v)| Some((map_key(k, opts)?, v)))
+            .collect();
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+        for (k, v) in keyed {
+            emit(k, v);
+        }
+    } else {
+        for (k, v) in map {
+            if let Some(k) = map_key(k, opts) {
+                emit(k, v);
+            }
+        }
+    }
+}
+
+/// A map's entries as a TOML table, for [`FieldInfo::value`]. Panics on a
+/// key with no TOML form, which rendering would have reported.
+#[doc(hidden)]
+pub fn map_table<'a, K, V>(map: impl IntoIterator<Item = (&'a K, &'a V)>) -> toml::Table
+where
+    K: serde::Serialize + 'a,
+    V: serde::Serialize + 'a,
+{
+    let mut table = toml::Table::new();
+    for_each_map_entry(map, false, &RenderOptions::default(), |k, v| {
+        table.insert(k, v);
+    });
+    table
+}
+
+fn map_key(key: &impl serde::Serialize, opts: &RenderOptions) -> Option<String> {
+    let key = match toml::Value::try_from(key) {
+        Ok(toml::Value::String(s)) => return Some(s),
+        Ok(toml::Value::Integer(i)) => return Some(i.to_string()),
+        Ok(toml::Value::Boolean(b)) => return Some(b.to_string()),
+        Ok(other) => fmt_value(&other),
+        Err(err) => format!("({err})"),
+    };
+    report(opts, RenderError::MapKey { key });
+    None
+}
+
+/// The `type_hints` name of a value as written: `string`, `array of
+/// integers`, ... `None` for an array mixing types.
+#[doc(hidden)]
+pub fn toml_type_of(val: &toml::Value) -> Option<String> {
+    let name = match val {
+        toml::Value::String(_) => "string",
+        toml::Value::Integer(_) => "integer",
+        toml::Value::Float(_) => "float",
+        toml::Value::Boolean(_) => "boolean",
+        toml::Value::Datetime(_) => "datetime",
+        toml::Value::Table(_) => "table",
+        toml::Value::Array(items) => {
+            let Some(first) = items.first() else {
+                return Some("array".to_string());
+            };
+            let elem = toml_type_of(first)?;
+            if items
+                .iter()
+                .any(|item| toml_type_of(item).as_ref() != Some(&elem))
+            {
+                return None;
+            }
+            return Some(match elem.split_once(' ') {
+                Some((head, rest)) => format!("array of {head}s {rest}"),
+                None => format!("array of {elem}s"),
+            });
+        }
+    };
+    Some(name.to_string())
+}
+
+/// Sorts a set's elements, which `HashSet` and `BinaryHeap` yield in no
+/// stable order. Numbers compare by value, strings and booleans as themselves, anything
+/// else by its TOML text.
+#[doc(hidden)]
+pub fn sort_set(value: toml::Value) -> toml::Value {
+    let toml::Value::Array(mut items) = value else {
+        return value;
+    };
+    items.sort_by(|a, b| match (a, b) {
+        (toml::Value::Integer(a), toml::Value::Integer(b)) => a.cmp(b),
+        (toml::Value::Float(a), toml::Value::Float(b)) => a.total_cmp(b),
+        (toml::Value::String(a), toml::Value::String(b)) => a.cmp(b),
+        (toml::Value::Boolean(a), toml::Value::Boolean(b)) => a.cmp(b),
+        _ => fmt_value(a).cmp(&fmt_value(b)),
+    });
+    toml::Value::Array(items)
+}
+
+/// `Some(T::default())` when `T` implements `Default`, `None` otherwise,
+/// picked at compile time by method resolution: with `ViaDefault` and
+/// `NoDefault` in scope, `(&&DefaultOf::<T>::new()).value()` finds the
+/// `&DefaultOf` impl first and derefs to the `DefaultOf` one only when
+/// `T: Default` doesn't hold. Only works for concrete types.
+#[doc(hidden)]
+pub struct DefaultOf<T>(std::marker::PhantomData<T>);
+
+impl<T> DefaultOf<T> {
+    pub fn new() -> Self {
+        Self(std::marker::PhantomData)
+    }
+}
+
+impl<T> Default for DefaultOf<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaDefault<T> {
+    fn value(&self) -> Option<T>;
+}
+
+impl<T: Default> ViaDefault<T> for &DefaultOf<T> {
+    fn value(&self) -> Option<T> {
+        Some(T::default())
+    }
+}
+
+#[doc(hidden)]
+pub trait NoDefault<T> {
+    fn value(&self) -> Option<T>;
+}
+
+impl<T> NoDefault<T> for DefaultOf<T> {
+    fn value(&self) -> Option<T> {
+        None
+    }
+}
+
+#[doc(hidden)]
+pub type KeyDocs = dyn Fn(&str) -> Option<String>;
+
+/// Renders map entries under `[section]`: plain values first, then every
+/// table value as its own `[section.key]` subsection, recursively. Nested
+/// tables are walked in place rather than copied out.
+#[doc(hidden)]
+pub fn push_map_tables<'a>(
+    out: &mut String,
+    opts: &RenderOptions,
+    section: &str,
+    entries: impl IntoIterator<Item = (&'a String, &'a toml::Value)>,
+    key_docs: Option<&KeyDocs>,
+) {
+    let margin_opts = RenderOptions {
+        in_table: false,
+        ..*opts
+    };
+    let (tables, plain): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|(_, v)| matches!(v, toml::Value::Table(_)));
+    let mut wrote = false;
+    if !plain.is_empty() {
+        push_header(out, opts, section, false);
+        let opts = &RenderOptions {
+            in_table: true,
+            ..*opts
+        };
+        for (k, v) in plain {
+            if let Some(doc) = key_docs.and_then(|key_docs| key_docs(k)) {
+                push_docs(out, opts, None, &[doc.as_str()], &[]);
+            }
+            push_entry(out, k, v, &NumberFormat::default(), opts);
+        }
+        wrote = true;
+    }
+    for (k, v) in tables {
+        let toml::Value::Table(table) = v else {
+            continue;
+        };
+        if wrote {
+            push_gap(out, opts);
+        }
+        wrote = true;
+        if let Some(doc) = key_docs.and_then(|key_docs| key_docs(k)) {
+            push_docs(out, &margin_opts, None, &[doc.as_str()], &[]);
+        }
+        let section = format!("{section}.{}", fmt_key(k));
+        if table.is_empty() {
+            push_header(out, opts, &section, false);
+        } else {
+            push_map_tables(out, opts, &section, table, None);
+        }
+    }
+}
+
+/// A plain `key = value` field as a row of the table the derive emits per
+/// struct, so ordinary fields share [`push_leaf`] instead of each getting
+/// their own copy of the rendering code.
+#[doc(hidden)]
+pub struct Leaf<T> {
+    pub key: &'static str,
+    pub docs: &'static [&'static str],
+    /// Fixed lines after the docs: unit, aliases, `requires`, ...
+    pub notes: &'static [&'static str],
+    pub type_hint: Option<&'static str>,
+    /// The `env:` and `range:` lines `annotate` adds after the default.
+    pub annotations: &'static [&'static str],
+    pub format: NumberFormat,
+    pub value: fn(&T) -> Result<toml::Value, toml::ser::Error>,
+    pub default: fn() -> Option<toml::Value>,
+}
+
+#[doc(hidden)]
+pub fn push_leaf<T>(out: &mut String, opts: &RenderOptions, this: &T, leaf: &Leaf<T>) {
+    push_docs(out, opts, None, leaf.docs, &[]);
+    if !leaf.notes.is_empty() {
+        push_docs(out, opts, None, leaf.notes, &[]);
+    }
+    if opts.type_hints
+        && let Some(hint) = leaf.type_hint
+    {
+        push_docs(out, opts, None, &[hint], &[]);
+    }
+    if opts.annotate {
+        let default = (leaf.default)().map(|val| format!("default: {}", fmt_value(&val)));
+        let mut lines: Vec<&str> = default.iter().map(String::as_str).collect();
+        lines.extend(leaf.annotations);
+        push_docs(out, opts, None, &lines, &[]);
+    }
+    let val = (leaf.value)(this).unwrap();
+    let key = prefixed_key(opts.key_prefix, leaf.key);
+    push_entry(out, &key, &val, &leaf.format, opts);
+}
+
+/// Widest `key = value` line [`push_entry`] writes when
+/// `RenderOptions::line_width` is unset.
+const LINE_WIDTH: usize = 80;
+
+/// Writes `key = value` and a newline. When the line would not fit in the
+/// line width, an array is spread over one element per line (nested arrays
+/// too, as needed) and a string with spaces is wrapped into a `"""` string
+/// whose line-ending backslashes join it back together.
+#[doc(hidden)]
+pub fn push_entry(
+    out: &mut String,
+    key: &str,
+    val: &toml::Value,
+    format: &NumberFormat,
+    opts: &RenderOptions,
+) {
+    push_dotted_entry(out, &[key], val, format, opts);
+}
+
+/// [`push_entry`] under a dotted key. An inline table too long for the
+/// line is written as one dotted key per entry instead (`main.port = 3000`),
+/// which TOML reads back as the same table without opening a new section.
+fn push_dotted_entry(
+    out: &mut String,
+    path: &[&str],
+    val: &toml::Value,
+    format: &NumberFormat,
+    opts: &RenderOptions,
+) {
+    let start = out.len();
+    push_indent(out, opts);
+    for (i, key) in path.iter().enumerate() {
+        if i > 0 {
+            out.push('.');
+        }
+        push_key(out, key);
+    }
+    let key_end = out.len();
+    out.push_str(" = ");
+    push_entry_value(out, start, val, format, opts);
+    if let toml::Value::Table(table) = val
+        && !table.is_empty()
+        && out.len() - start > opts.line_width.unwrap_or(LINE_WIDTH)
+    {
+        out.truncate(start);
+        for (key, val) in table {
+            let path: Vec<&str> = path.iter().copied().chain([key.as_str()]).collect();
+            push_dotted_entry(out, &path, val, format, opts);
+        }
+        return;
+    }
+    out.push('\n');
+    if pad(opts) > 0 {
+        indent_continued(out, start, pad(opts));
+    }
+    if let Some(log) = opts.line_log {
+        log.entry(start, key_end, out.len() - 1);
+    }
+}
+
+/// The value of an entry whose line starts at `start`.
+fn push_entry_value(
+    out: &mut String,
+    start: usize,
+    val: &toml::Value,
+    format: &NumberFormat,
+    opts: &RenderOptions,
+) {
+    let table_like = match val {
+        toml::Value::Table(_) => true,
+        toml::Value::Array(items) => !items.is_empty() && items.iter().all(toml::Value::is_table),
+        _ => false,
+    };
+    if opts.toml_pretty
+        && !table_like
+        && *format == NumberFormat::default()
+        && !opts.scientific_floats
+    {
+        // Written under a stand-in key, so only the value is toml's.
+        let entry = toml::Table::from_iter([("k".to_string(), val.clone())]);
+        if let Ok(pretty) = toml::to_string_pretty(&entry)
+            && let Some(value) = pretty.strip_prefix("k = ")
+        {
+            out.push_str(value.trim_end_matches('\n'));
+            return;
+        }
+    }
+    let value_start = out.len();
+    push_number(out, val, format, opts);
+    let width = opts.line_width.unwrap_or(LINE_WIDTH);
+    if out.len() - start > width {
+        let indent = " ".repeat(opts.array_indent.unwrap_or(4));
+        match val {
+            toml::Value::Array(items) if !items.is_empty() => {
+                out.truncate(value_start);
+                push_long_array(out, items, format, opts, &indent, 1, width);
+            }
+            toml::Value::String(s) => {
+                if let Some(wrapped) = wrap_string(s, &indent, width) {
+                    out.truncate(value_start);
+                    out.push_str(&wrapped);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Indents the lines after the first of the entry written from `start`,
+/// such as a long array's elements and closing bracket. Lines that start
+/// inside a `"""` or `'''` string are part of the value and stay as they are.
+fn indent_continued(out: &mut String, start: usize, width: usize) {
+    let entry = out.split_off(start);
+    let bytes = entry.as_bytes();
+    // The delimiter of the string being read, if any.
+    let mut string: Option<&[u8]> = None;
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        match string {
+            None if rest.starts_with(b"\"\"\"") || rest.starts_with(b"'''") => {
+                string = Some(&rest[..3]);
+                i += 3;
+            }
+            None if rest[0] == b'"' || rest[0] == b'\'' => {
+                string = Some(&rest[..1]);
+                i += 1;
+            }
+            None => {
+                i += 1;
+                if rest[0] == b'\n' && i < bytes.len() {
+                    out.push_str(&entry[copied..i]);
+                    out.extend(std::iter::repeat_n(' ', width));
+                    copied = i;
+                }
+            }
+            Some(delim) if delim[0] == b'"' && rest[0] == b'\\' => i += 2,
+            Some(delim) if rest.starts_with(delim) => {
+                i += delim.len();
+                // A closing `"""` can follow up to two quotes of the string's
+                // own; the delimiter is the last three.
+                let mut extra = 0;
+                while delim.len() == 3 && extra < 2 && bytes.get(i) == Some(&delim[0]) {
+                    i += 1;
+                    extra += 1;
+                }
+                string = None;
+            }
+            Some(_) => i += 1,
+        }
+    }
+    out.push_str(&entry[copied..]);
+}
+
+fn push_long_array(
+    out: &mut String,
+    items: &[toml::Value],
+    format: &NumberFormat,
+    opts: &RenderOptions,
+    indent: &str,
+    depth: usize,
+    width: usize,
+) {
+    out.push_str("[\n");
+    for item in items {
+        let line_start = out.len();
+        for _ in 0..depth {
+            out.push_str(indent);
+        }
+        let value_start = out.len();
+        push_number(out, item, format, opts);
+        if let toml::Value::Array(inner) = item
+            && !inner.is_empty()
+            && out.len() - line_start + 1 > width
+        {
+            out.truncate(value_start);
+            push_long_array(out, inner, format, opts, indent, depth + 1, width);
+        }
+        out.push_str(",\n");
+    }
+    for _ in 1..depth {
+        out.push_str(indent);
+    }
+    out.push(']');
+}
+
+/// Splits a string after runs of spaces into an indented `"""` string.
+/// Whitespace at the split points stays before the line-ending backslash,
+/// which TOML drops along with the newline and the next line's indent, so
+/// the value reads back unchanged. `None` when there's nowhere to split.
+fn wrap_string(s: &str, indent: &str, width: usize) -> Option<String> {
+    if !s.contains(' ') || s.starts_with(char::is_whitespace) {
+        return None;
+    }
+    // Words with their trailing whitespace, so joining them restores `s`.
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut in_space = false;
+    for (i, c) in s.char_indices() {
+        if c == ' ' {
+            in_space = true;
+        } else if in_space {
+            words.push(&s[start..i]);
+            start = i;
+            in_space = false;
+        }
+    }
+    words.push(&s[start..]);
+    if words.len() < 2 {
+        return None;
+    }
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in words {
+        let word = escape_basic(word);
+        if !line.is_empty() && indent.len() + line.len() + word.len() + 1 > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        line.push_str(&word);
+    }
+    lines.push(line);
+    let body: Vec<String> = lines.iter().map(|line| format!("{indent}{line}")).collect();
+    Some(format!("\"\"\"\\\n{}\"\"\"", body.join("\\\n")))
+}
+
+/// The inside of a basic string: quotes, backslashes and control
+/// characters escaped.
+fn escape_basic(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    push_escaped(&mut out, s);
+    out
+}
+
+fn push_escaped(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04X}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+/// How `#[toml_comment(format = "hex", separators, precision = 2,
+/// scientific)]` writes a field's numbers. Arrays apply it to each element.
+#[doc(hidden)]
+#[derive(PartialEq)]
+pub struct NumberFormat {
+    pub radix: u32,
+    pub separators: bool,
+    pub precision: Option<usize>,
+    pub scientific: bool,
+}
+
+impl NumberFormat {
+    /// Plain decimal, usable in the derive's `const` field tables.
+    pub const DEFAULT: Self = Self {
+        radix: 10,
+        separators: false,
+        precision: None,
+        scientific: false,
+    };
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[doc(hidden)]
+pub fn fmt_number(val: &toml::Value, format: &NumberFormat, opts: &RenderOptions) -> String {
+    let mut out = String::new();
+    push_number(&mut out, val, format, opts);
+    out
+}
+
+fn push_number(out: &mut String, val: &toml::Value, format: &NumberFormat, opts: &RenderOptions) {
+    match val {
+        toml::Value::Integer(i) => push_integer(out, *i, format),
+        toml::Value::Float(f)
+            if (format.scientific || opts.scientific_floats)
+                && f.is_finite()
+                && (f.abs() >= SCIENTIFIC_ABOVE || (*f != 0.0 && f.abs() < SCIENTIFIC_BELOW)) =>
+        {
+            let _ = match format.precision {
+                Some(precision) => write!(out, "{f:.precision$e}"),
+                None => write!(out, "{f:e}"),
+            };
+        }
+        toml::Value::Float(f) if f.is_finite() => match format.precision {
+            Some(precision) => out.push_str(&fmt_float(*f, precision)),
+            None => push_value(out, val),
+        },
+        toml::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                push_number(out, item, format, opts);
+            }
+            out.push(']');
+        }
+        _ => push_value(out, val),
+    }
+}
+
+/// Magnitudes written as `1.5e9` / `2e-5` under the `scientific` format.
+const SCIENTIFIC_ABOVE: f64 = 1e6;
+const SCIENTIFIC_BELOW: f64 = 1e-4;
+
+/// Rounds to `precision` decimals, then drops trailing zeros down to `.0`
+/// so `0.75` stays `0.75` and `1.0` stays a float.
+fn fmt_float(f: f64, precision: usize) -> String {
+    let s = format!("{f:.precision$}");
+    match s.split_once('.') {
+        Some((whole, frac)) => {
+            let frac = frac.trim_end_matches('0');
+            format!("{whole}.{}", if frac.is_empty() { "0" } else { frac })
+        }
+        None => format!("{s}.0"),
+    }
+}
+
+/// TOML only has `0x`/`0o`/`0b` literals for non-negative integers, so
+/// negative ones stay decimal.
+fn push_integer(out: &mut String, i: i64, format: &NumberFormat) {
+    if !format.separators {
+        let _ = match format.radix {
+            16 if i >= 0 => write!(out, "0x{i:x}"),
+            8 if i >= 0 => write!(out, "0o{i:o}"),
+            2 if i >= 0 => write!(out, "0b{i:b}"),
+            _ => write!(out, "{i}"),
+        };
+        return;
+    }
+    let (prefix, digits, group) = match format.radix {
+        16 if i >= 0 => ("0x", format!("{i:x}"), 4),
+        8 if i >= 0 => ("0o", format!("{i:o}"), 4),
+        2 if i >= 0 => ("0b", format!("{i:b}"), 4),
+        _ if i < 0 => ("-", i.unsigned_abs().to_string(), 3),
+        _ => ("", i.to_string(), 3),
+    };
+    out.push_str(prefix);
+    for (n, c) in digits.chars().enumerate() {
+        if n > 0 && (digits.len() - n) % group == 0 {
+            out.push('_');
+        }
+        out.push(c);
+    }
+}
+
+pub fn fmt_key(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    push_key(&mut out, key);
+    out
+}
+
+fn push_key(out: &mut String, key: &str) {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        out.push_str(key);
+    } else {
+        out.push('"');
+        push_escaped(out, key);
+        out.push('"');
+    }
 }
 
 pub fn fmt_value(val: &toml::Value) -> String {
+    let mut out = String::new();
+    push_value(&mut out, val);
+    out
+}
+
+/// Writes `val` as inline TOML straight into `out`, so arrays and tables
+/// don't build a string per element.
+fn push_value(out: &mut String, val: &toml::Value) {
     match val {
-        toml::Value::String(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
-        toml::Value::Integer(i) => i.to_string(),
+        toml::Value::String(s) => {
+            out.push('"');
+            push_escaped(out, s);
+            out.push('"');
+        }
+        toml::Value::Integer(i) => {
+            let _ = write!(out, "{i}");
+        }
         toml::Value::Float(f) => {
-            let s = f.to_string();
-            if s.contains('.') { s } else { format!("{s}.0") }
+            let start = out.len();
+            let _ = write!(out, "{f}");
+            if !out[start..].contains('.') {
+                out.push_str(".0");
+            }
         }
-        toml::Value::Boolean(b) => b.to_string(),
+        toml::Value::Boolean(b) => out.push_str(if *b { "true" } else { "false" }),
         toml::Value::Array(arr) => {
-            format!(
-                "[{}]",
-                arr.iter().map(fmt_value).collect::<Vec<_>>().join(", ")
-            )
+            out.push('[');
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                push_value(out, item);
+            }
+            out.push(']');
         }
+        toml::Value::Table(t) if t.is_empty() => out.push_str("{}"),
         toml::Value::Table(t) => {
-            let pairs = t.iter().map(|(k, v)| format!("{k} = {}", fmt_value(v)));
-            format!("{{ {} }}", pairs.collect::<Vec<_>>().join(", "))
+            out.push_str("{ ");
+            for (i, (k, v)) in t.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                push_key(out, k);
+                out.push_str(" = ");
+                push_value(out, v);
+            }
+            out.push_str(" }");
+        }
+        toml::Value::Datetime(dt) => {
+            let _ = write!(out, "{dt}");
         }
-        toml::Value::Datetime(dt) => dt.to_string(),
     }
 }
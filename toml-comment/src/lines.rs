@@ -0,0 +1,109 @@
+use std::cell::RefCell;
+use std::fmt;
+
+/// One line of rendered TOML, as yielded by
+/// [`TomlComment::lines`](crate::TomlComment::lines). Indentation from
+/// `RenderOptions::indent` is left off; everything else is as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderedLine {
+    Blank,
+    /// A comment line with its prefix, e.g. `# Port to listen on`. Unset
+    /// keys and absent tables shown as `# key = <string>` are comments too.
+    Comment(String),
+    /// A `key = value` entry. Long arrays and wrapped strings keep their
+    /// line breaks in `value`, so one entry can cover several lines.
+    KeyValue {
+        key: String,
+        value: String,
+    },
+    /// A `[path]` header, or `[[path]]` when `array` is set.
+    SectionHeader {
+        path: String,
+        array: bool,
+    },
+}
+
+impl fmt::Display for RenderedLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderedLine::Blank => Ok(()),
+            RenderedLine::Comment(text) => f.write_str(text),
+            RenderedLine::KeyValue { key, value } => write!(f, "{key} = {value}"),
+            RenderedLine::SectionHeader { path, array: false } => write!(f, "[{path}]"),
+            RenderedLine::SectionHeader { path, array: true } => write!(f, "[[{path}]]"),
+        }
+    }
+}
+
+/// What each line written during a render is, noted by the code that
+/// writes it, so [`TomlComment::lines`](crate::TomlComment::lines) never
+/// has to guess from the text.
+#[doc(hidden)]
+#[derive(Default)]
+pub struct LineLog(RefCell<Vec<Mark>>);
+
+/// One logged line: `out[start..end]`, without its newline.
+struct Mark {
+    start: usize,
+    end: usize,
+    kind: Kind,
+}
+
+enum Kind {
+    Comment,
+    /// `key_end` is where the key stops and ` = ` starts.
+    Entry {
+        key_end: usize,
+    },
+    Header {
+        array: bool,
+    },
+}
+
+impl LineLog {
+    pub(crate) fn comment(&self, start: usize, end: usize) {
+        self.push(start, end, Kind::Comment);
+    }
+
+    pub(crate) fn entry(&self, start: usize, key_end: usize, end: usize) {
+        self.push(start, end, Kind::Entry { key_end });
+    }
+
+    pub(crate) fn header(&self, start: usize, end: usize, array: bool) {
+        self.push(start, end, Kind::Header { array });
+    }
+
+    fn push(&self, start: usize, end: usize, kind: Kind) {
+        self.0.borrow_mut().push(Mark { start, end, kind });
+    }
+
+    /// Splits `toml` at the logged lines. Whatever lies between them is
+    /// the blank lines of section gaps.
+    pub(crate) fn split(self, toml: &str) -> Vec<RenderedLine> {
+        let mut lines = Vec::new();
+        let mut pos = 0;
+        for mark in self.0.into_inner() {
+            let blanks = toml[pos..mark.start].matches('\n').count();
+            lines.extend(std::iter::repeat_n(RenderedLine::Blank, blanks));
+            let text = toml[mark.start..mark.end].trim_start();
+            lines.push(match mark.kind {
+                Kind::Comment => RenderedLine::Comment(text.to_string()),
+                Kind::Entry { key_end } => RenderedLine::KeyValue {
+                    key: toml[mark.start..key_end].trim_start().to_string(),
+                    value: toml[key_end + " = ".len()..mark.end].to_string(),
+                },
+                Kind::Header { array } => {
+                    let brackets = if array { 2 } else { 1 };
+                    RenderedLine::SectionHeader {
+                        path: text[brackets..text.len() - brackets].to_string(),
+                        array,
+                    }
+                }
+            });
+            pos = (mark.end + 1).min(toml.len());
+        }
+        let blanks = toml[pos..].matches('\n').count();
+        lines.extend(std::iter::repeat_n(RenderedLine::Blank, blanks));
+        lines
+    }
+}
@@ -0,0 +1,579 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::{FieldKind, TomlComment, fmt_key};
+
+/// How [`Profiles::load_with`](crate::Profiles::load_with) and
+/// [`load_files_with`](crate::load_files_with) read their input. The
+/// default reads it exactly as `load` and `load_files` do.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadOptions {
+    /// Match keys that differ from the config's own only in case or in `-`
+    /// versus `_`, so `Max-Connections` fills `max_connections`. Only keys
+    /// the default config lists are known; map keys are left alone.
+    /// [`fold_keys`] does the same to a file's text.
+    pub fold_keys: bool,
+    pub unknown_keys: UnknownKeys,
+    pub aliases: Aliases,
+}
+
+/// What loading does with keys the config doesn't read.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum UnknownKeys {
+    /// Hand them to serde, which skips them unless the type has
+    /// `#[serde(deny_unknown_fields)]`. A `#[serde(flatten)]` field of type
+    /// `toml::Table` keeps them instead.
+    #[default]
+    Ignore,
+    /// Call the function with each one's path, then load as `Ignore` does.
+    Warn(fn(&str)),
+    /// Fail the load, naming every one of them.
+    Deny,
+}
+
+/// Whether loading accepts the old names from `#[serde(alias)]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aliases {
+    #[default]
+    Accept,
+    /// Treat them as unknown keys: removed before serde sees them, and
+    /// reported as [`UnknownKeys`] says.
+    Reject,
+}
+
+/// What loading with `opts` needs to know about `T`, built once per load.
+pub(crate) struct Loader {
+    schema: Option<Schema>,
+    opts: LoadOptions,
+}
+
+impl Loader {
+    pub(crate) fn new<T: TomlComment + Default>(opts: &LoadOptions) -> Self {
+        let needs_schema = opts.fold_keys
+            || !matches!(opts.unknown_keys, UnknownKeys::Ignore)
+            || opts.aliases == Aliases::Reject;
+        Self {
+            schema: needs_schema.then(|| Schema::of::<T>(opts.aliases)),
+            opts: *opts,
+        }
+    }
+
+    /// Prepares one parsed file or profile table before it is merged with
+    /// the others.
+    pub(crate) fn prepare(&self, table: &mut toml::Table) {
+        if let Some(schema) = self.schema.as_ref().filter(|_| self.opts.fold_keys) {
+            schema.fold(table, "");
+        }
+    }
+
+    pub(crate) fn finish<T>(&self, mut table: toml::Table) -> Result<T, toml::de::Error>
+    where
+        T: TomlComment + serde::de::DeserializeOwned,
+    {
+        let Some(schema) = &self.schema else {
+            return toml::Value::Table(table).try_into();
+        };
+        let mut unknown = Vec::new();
+        if self.opts.aliases == Aliases::Reject {
+            schema.remove_aliases(&mut table, "", "", &mut unknown);
+        }
+        let read = match self.opts.unknown_keys {
+            UnknownKeys::Ignore => toml::Value::Table(table)
+                .try_into()
+                .map(|config| (config, Vec::new())),
+            _ => schema.read::<T>(table),
+        };
+        let deny = matches!(self.opts.unknown_keys, UnknownKeys::Deny);
+        let config = match read {
+            Ok((config, more)) => {
+                unknown.extend(more);
+                config
+            }
+            // A rejected alias can leave a required field missing; the
+            // alias is the better thing to report.
+            Err(_) if deny && !unknown.is_empty() => return Err(unknown_error(&unknown)),
+            Err(err) => return Err(err),
+        };
+        match self.opts.unknown_keys {
+            UnknownKeys::Deny if !unknown.is_empty() => Err(unknown_error(&unknown)),
+            UnknownKeys::Warn(warn) => {
+                unknown.iter().for_each(|key| warn(key));
+                Ok(config)
+            }
+            _ => Ok(config),
+        }
+    }
+}
+
+fn unknown_error(keys: &[String]) -> toml::de::Error {
+    serde::de::Error::custom(format!("unknown keys: {}", keys.join(", ")))
+}
+
+/// The keys in `text` that `T` doesn't read, by dotted path with `[n]` for
+/// elements of arrays of tables, so a caller can warn about typos and
+/// settings from newer versions while still loading the file.
+pub fn unknown_keys<T>(text: &str) -> Result<Vec<String>, toml::de::Error>
+where
+    T: TomlComment + Default + serde::de::DeserializeOwned,
+{
+    let (_, unknown) = Schema::of::<T>(Aliases::Accept).read::<T>(text.parse()?)?;
+    Ok(unknown)
+}
+
+/// `text` with the keys [`LoadOptions::fold_keys`] would match renamed to
+/// the config's own spelling in place, comments and layout untouched, so a
+/// leniently loaded file can be written back in canonical form. A key is
+/// left as written where its table already spells it the config's way.
+pub fn fold_keys<T: TomlComment + Default>(text: &str) -> Result<String, toml::de::Error> {
+    text.parse::<toml::Table>()?;
+    let schema = Schema::of::<T>(Aliases::Accept);
+    let mut scan = KeyScan {
+        text: text.as_bytes(),
+        pos: 0,
+        schema: &schema,
+        table: 0,
+        path: String::new(),
+        tables: 0,
+        keys: Vec::new(),
+    };
+    scan.document();
+
+    let spelled: HashSet<(usize, &str, &str)> = scan
+        .keys
+        .iter()
+        .filter(|key| key.rename.is_none())
+        .map(|key| (key.table, key.parent.as_str(), key.name.as_str()))
+        .collect();
+    let mut taken = HashSet::new();
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    for key in &scan.keys {
+        let Some(to) = key.rename else {
+            continue;
+        };
+        let slot = (key.table, key.parent.as_str(), to);
+        if spelled.contains(&slot) || !taken.insert(slot) {
+            continue;
+        }
+        out.push_str(&text[pos..key.span.start]);
+        out.push_str(&fmt_key(to));
+        pos = key.span.end;
+    }
+    out.push_str(&text[pos..]);
+    Ok(out)
+}
+
+/// One key as written in a file, with where it sits: `table` tells apart
+/// the tables a header or inline table opens, `parent` is the schema path
+/// the key lives under.
+struct ScannedKey<'a> {
+    table: usize,
+    parent: String,
+    name: String,
+    span: Range<usize>,
+    rename: Option<&'a str>,
+}
+
+/// Walks the keys of a TOML document already known to parse, skipping over
+/// values without reading them.
+struct KeyScan<'a> {
+    text: &'a [u8],
+    pos: usize,
+    schema: &'a Schema,
+    /// The table the last header opened, and its schema path.
+    table: usize,
+    path: String,
+    tables: usize,
+    keys: Vec<ScannedKey<'a>>,
+}
+
+/// Header keys all name tables of the document's one tree, whichever
+/// header they appear in.
+const HEADER_KEYS: usize = usize::MAX;
+
+impl<'a> KeyScan<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.text[self.pos..].starts_with(prefix)
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skips whitespace, line breaks and comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\r' | b'\n') => self.pos += 1,
+                Some(b'#') => self.skip_until(b"\n"),
+                _ => return,
+            }
+        }
+    }
+
+    fn skip_until(&mut self, stops: &[u8]) {
+        while self.peek().is_some_and(|c| !stops.contains(&c)) {
+            self.pos += 1;
+        }
+    }
+
+    fn document(&mut self) {
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => return,
+                Some(b'[') => {
+                    let array = self.starts_with(b"[[");
+                    self.pos += if array { 2 } else { 1 };
+                    self.path = self.keys(HEADER_KEYS, "");
+                    self.pos += if array { 2 } else { 1 };
+                    self.tables += 1;
+                    self.table = self.tables;
+                }
+                Some(_) => {
+                    let path = std::mem::take(&mut self.path);
+                    self.key_value(self.table, &path);
+                    self.path = path;
+                }
+            }
+        }
+    }
+
+    fn key_value(&mut self, table: usize, parent: &str) {
+        let path = self.keys(table, parent);
+        self.skip_spaces();
+        self.pos += 1; // `=`
+        self.skip_spaces();
+        self.value(&path);
+    }
+
+    /// Reads a dotted key under `parent`, returning its schema path.
+    fn keys(&mut self, table: usize, parent: &str) -> String {
+        let mut path = parent.to_string();
+        loop {
+            self.skip_spaces();
+            let start = self.pos;
+            let name = self.key();
+            let span = start..self.pos;
+            let rename = self.schema.canonical(&path, &name);
+            let child = join(&path, rename.unwrap_or(&name));
+            let parent = std::mem::replace(&mut path, child);
+            self.keys.push(ScannedKey {
+                table,
+                parent,
+                name,
+                span,
+                rename,
+            });
+            self.skip_spaces();
+            if self.peek() != Some(b'.') {
+                return path;
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// One bare or quoted key, unquoted.
+    fn key(&mut self) -> String {
+        let start = self.pos;
+        match self.peek() {
+            Some(b'"' | b'\'') => {
+                self.string();
+                let quoted = String::from_utf8_lossy(&self.text[start..self.pos]);
+                let table: Option<toml::Table> = format!("{quoted} = 0").parse().ok();
+                table
+                    .and_then(|table| table.into_iter().next())
+                    .map(|(key, _)| key)
+                    .unwrap_or_default()
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_' || c == b'-')
+                {
+                    self.pos += 1;
+                }
+                String::from_utf8_lossy(&self.text[start..self.pos]).into_owned()
+            }
+        }
+    }
+
+    /// Skips a string of any of the four kinds.
+    fn string(&mut self) {
+        for (delim, escapes) in [(&b"\"\"\""[..], true), (b"'''", false)] {
+            if self.starts_with(delim) {
+                self.pos += 3;
+                while !self.starts_with(delim) && self.peek().is_some() {
+                    self.pos += if escapes && self.peek() == Some(b'\\') {
+                        2
+                    } else {
+                        1
+                    };
+                }
+                self.pos += 3;
+                // Up to two quotes before the closing ones are content.
+                for _ in 0..2 {
+                    if self.peek() == Some(delim[0]) {
+                        self.pos += 1;
+                    }
+                }
+                return;
+            }
+        }
+        let quote = self.text[self.pos];
+        self.pos += 1;
+        while self.peek().is_some_and(|c| c != quote) {
+            self.pos += if quote == b'"' && self.peek() == Some(b'\\') {
+                2
+            } else {
+                1
+            };
+        }
+        self.pos += 1;
+    }
+
+    fn value(&mut self, path: &str) {
+        match self.peek() {
+            Some(b'"' | b'\'') => self.string(),
+            Some(b'[') => {
+                self.pos += 1;
+                loop {
+                    self.skip_trivia();
+                    match self.peek() {
+                        None => return,
+                        Some(b']') => break,
+                        Some(b',') => self.pos += 1,
+                        Some(_) => self.value(path),
+                    }
+                }
+                self.pos += 1;
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                self.tables += 1;
+                let table = self.tables;
+                loop {
+                    self.skip_spaces();
+                    match self.peek() {
+                        None => return,
+                        Some(b'}') => break,
+                        Some(b',') => self.pos += 1,
+                        Some(_) => self.key_value(table, path),
+                    }
+                }
+                self.pos += 1;
+            }
+            _ => self.skip_until(b",]}#\r\n"),
+        }
+    }
+}
+
+fn join(base: &str, key: &str) -> String {
+    if base.is_empty() {
+        key.to_string()
+    } else {
+        format!("{base}.{key}")
+    }
+}
+
+/// The keys of the default config, by dotted path without `[n]` indices,
+/// with the aliases serde also accepts for them.
+struct Schema {
+    keys: Vec<(String, FieldKind, &'static [&'static str])>,
+    aliases: Aliases,
+}
+
+impl Schema {
+    fn of<T: TomlComment + Default>(aliases: Aliases) -> Self {
+        let fields = T::default().fields();
+        Self {
+            keys: fields
+                .into_iter()
+                .map(|field| (strip_indices(&field.path), field.kind, field.aliases))
+                .collect(),
+            aliases,
+        }
+    }
+
+    /// The keys directly under `section`.
+    fn children<'a>(
+        &'a self,
+        section: &str,
+    ) -> impl Iterator<Item = (&'a str, FieldKind, &'static [&'static str])> {
+        self.keys.iter().filter_map(move |(path, kind, aliases)| {
+            let key = if section.is_empty() {
+                path.as_str()
+            } else {
+                path.strip_prefix(section)?.strip_prefix('.')?
+            };
+            (!key.contains('.')).then_some((key, *kind, *aliases))
+        })
+    }
+
+    /// The config's spelling of `key` under `section`, when it differs only
+    /// the way [`same_key`] allows or names an alias.
+    fn canonical(&self, section: &str, key: &str) -> Option<&str> {
+        let children: Vec<_> = self.children(section).collect();
+        if children.iter().any(|(name, _, _)| *name == key) {
+            return None;
+        }
+        let (name, _, _) = children.into_iter().find(|(name, _, aliases)| {
+            same_key(name, key)
+                || self.aliases == Aliases::Accept
+                    && aliases.iter().any(|alias| same_key(alias, key))
+        })?;
+        Some(name)
+    }
+
+    /// Deserializes `table`, along with the keys of it that went unread.
+    fn read<T>(&self, table: toml::Table) -> Result<(T, Vec<String>), toml::de::Error>
+    where
+        T: TomlComment + serde::de::DeserializeOwned,
+    {
+        let config: T = toml::Value::Table(table.clone()).try_into()?;
+        let read = match toml::Value::try_from(&config) {
+            Ok(toml::Value::Table(read)) => read,
+            _ => toml::Table::new(),
+        };
+        let mut unknown = Vec::new();
+        self.unknown(&table, &read, "", "", &mut unknown);
+        Ok((config, unknown))
+    }
+
+    /// Collects the keys of `input` that neither came back in `read`, the
+    /// loaded config serialized again, nor are keys or aliases of the
+    /// schema (those serde reads but may not write back). `section` is the
+    /// schema path, `shown` the path with indices for the message.
+    fn unknown(
+        &self,
+        input: &toml::Table,
+        read: &toml::Table,
+        section: &str,
+        shown: &str,
+        out: &mut Vec<String>,
+    ) {
+        for (key, value) in input {
+            let path = join(section, key);
+            let shown = join(shown, key);
+            match (value, read.get(key)) {
+                (toml::Value::Table(input), Some(toml::Value::Table(read))) => {
+                    self.unknown(input, read, &path, &shown, out);
+                }
+                (toml::Value::Array(input), Some(toml::Value::Array(read))) => {
+                    for (i, (input, read)) in input.iter().zip(read).enumerate() {
+                        if let (toml::Value::Table(input), toml::Value::Table(read)) = (input, read)
+                        {
+                            self.unknown(input, read, &path, &format!("{shown}[{i}]"), out);
+                        }
+                    }
+                }
+                (_, Some(_)) => {}
+                (_, None) => {
+                    let known = self
+                        .children(section)
+                        .any(|(name, _, aliases)| name == key || aliases.contains(&key.as_str()));
+                    if !known {
+                        out.push(shown);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes the keys of `table` spelled as an alias, collecting their
+    /// paths like [`Schema::unknown`] does.
+    fn remove_aliases(
+        &self,
+        table: &mut toml::Table,
+        section: &str,
+        shown: &str,
+        out: &mut Vec<String>,
+    ) {
+        for (name, kind, aliases) in self.children(section) {
+            for alias in aliases {
+                if table.remove(*alias).is_some() {
+                    out.push(join(shown, alias));
+                }
+            }
+            if !matches!(kind, FieldKind::Section | FieldKind::Tables) {
+                continue;
+            }
+            let (path, shown) = (join(section, name), join(shown, name));
+            match table.get_mut(name) {
+                Some(toml::Value::Table(inner)) => self.remove_aliases(inner, &path, &shown, out),
+                Some(toml::Value::Array(items)) => {
+                    for (i, item) in items.iter_mut().enumerate() {
+                        if let toml::Value::Table(inner) = item {
+                            self.remove_aliases(inner, &path, &format!("{shown}[{i}]"), out);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Renames the keys of `table` to the schema's spelling, recursing into
+    /// sections and arrays of tables.
+    fn fold(&self, table: &mut toml::Table, section: &str) {
+        let renames: Vec<(String, &str)> = table
+            .keys()
+            .filter_map(|key| Some((key.clone(), self.canonical(section, key)?)))
+            .collect();
+        for (from, to) in renames {
+            if !table.contains_key(to) {
+                let value = table.remove(&from).expect("listed key");
+                table.insert(to.to_string(), value);
+            }
+        }
+        for (name, kind, _) in self.children(section) {
+            if !matches!(kind, FieldKind::Section | FieldKind::Tables) {
+                continue;
+            }
+            let path = join(section, name);
+            match table.get_mut(name) {
+                Some(toml::Value::Table(inner)) => self.fold(inner, &path),
+                Some(toml::Value::Array(items)) => {
+                    for item in items {
+                        if let toml::Value::Table(inner) = item {
+                            self.fold(inner, &path);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn same_key(canonical: &str, key: &str) -> bool {
+    let fold = |c: char| {
+        if c == '-' {
+            '_'
+        } else {
+            c.to_ascii_lowercase()
+        }
+    };
+    canonical.chars().map(fold).eq(key.chars().map(fold))
+}
+
+/// `upstream[0].addr` → `upstream.addr`.
+fn strip_indices(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut in_index = false;
+    for c in path.chars() {
+        match c {
+            '[' => in_index = true,
+            ']' => in_index = false,
+            _ if !in_index => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
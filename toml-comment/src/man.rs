@@ -0,0 +1,54 @@
+use crate::{FieldInfo, FieldKind, fmt_value};
+
+pub(crate) fn render(fields: &[FieldInfo]) -> String {
+    let mut out = String::from(".SH CONFIGURATION\n");
+    for field in fields {
+        if field.kind == FieldKind::Section {
+            out.push_str(&format!(".SS [{}]\n", escape(&field.path)));
+            push_docs(&mut out, &field.expanded_docs());
+            continue;
+        }
+        if field.kind == FieldKind::Tables {
+            out.push_str(&format!(".SS [[{}]]\n", escape(&field.path)));
+            push_docs(&mut out, &field.expanded_docs());
+            continue;
+        }
+        let default = match &field.value {
+            Some(val) => format!("\\fB{}\\fR", escape(&fmt_value(val))),
+            None => "unset".to_string(),
+        };
+        out.push_str(".TP\n");
+        out.push_str(&format!(
+            "\\fB{}\\fR (\\fI{}\\fR, default: {default})\n",
+            escape(&field.path),
+            escape(field.ty)
+        ));
+        push_docs(&mut out, &field.expanded_docs());
+    }
+    out
+}
+
+fn push_docs(out: &mut String, docs: &[String]) {
+    for doc in docs {
+        let doc = doc.trim();
+        if doc.is_empty() {
+            out.push_str(".sp\n");
+        } else {
+            out.push_str(&escape_line(doc));
+            out.push('\n');
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\e").replace('-', "\\-")
+}
+
+fn escape_line(s: &str) -> String {
+    let s = s.replace('\\', "\\e");
+    if s.starts_with('.') || s.starts_with('\'') {
+        format!("\\&{s}")
+    } else {
+        s
+    }
+}
@@ -0,0 +1,167 @@
+//! Tool-owned regions inside a config file shared with humans:
+//!
+//! ```toml
+//! # BEGIN managed by myapp (sha256:3a7b...)
+//! ...
+//! # END managed
+//! ```
+//!
+//! The checksum covers everything between the markers, so a later run can
+//! tell whether someone edited the block by hand.
+
+const BEGIN: &str = "# BEGIN managed by ";
+const END: &str = "# END managed";
+
+/// What [`check_managed`] found in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManagedBlock {
+    /// No complete `BEGIN`/`END` pair.
+    Missing,
+    /// The block matches its checksum.
+    Intact,
+    /// The block was changed since it was written.
+    Edited,
+}
+
+pub(crate) fn wrap(owner: &str, body: &str) -> String {
+    format!(
+        "{BEGIN}{owner} (sha256:{})\n{body}{END}\n",
+        hex(&sha256(body.as_bytes()))
+    )
+}
+
+/// Swaps the managed block in `existing` for `body`, keeping its owner and
+/// everything around it. `None` when there is no block.
+pub(crate) fn replace(existing: &str, body: &str) -> Option<String> {
+    let region = find(existing)?;
+    let mut out = existing[..region.start].to_string();
+    out.push_str(&wrap(region.owner, body));
+    out.push_str(&existing[region.end..]);
+    Some(out)
+}
+
+/// The managed block in a file: its byte range including both markers,
+/// what its `BEGIN` line records and the text between the markers.
+struct Region<'a> {
+    start: usize,
+    end: usize,
+    owner: &'a str,
+    checksum: &'a str,
+    body: &'a str,
+}
+
+fn find(text: &str) -> Option<Region<'_>> {
+    let start = line_start(text, BEGIN)?;
+    let header_end = text[start..].find('\n').map(|i| start + i + 1)?;
+    let header = text[start + BEGIN.len()..header_end].trim_end();
+    let (owner, checksum) = header.split_once(" (sha256:")?;
+    let checksum = checksum.strip_suffix(')')?;
+    let end_start = header_end + line_start(&text[header_end..], END)?;
+    let end = text[end_start..]
+        .find('\n')
+        .map_or(text.len(), |i| end_start + i + 1);
+    Some(Region {
+        start,
+        end,
+        owner,
+        checksum,
+        body: &text[header_end..end_start],
+    })
+}
+
+fn line_start(text: &str, marker: &str) -> Option<usize> {
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.starts_with(marker) {
+            return Some(offset);
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Checks the managed block in `text` against its recorded checksum.
+pub fn check_managed(text: &str) -> ManagedBlock {
+    match find(text) {
+        None => ManagedBlock::Missing,
+        Some(region) if region.checksum == hex(&sha256(region.body.as_bytes())) => {
+            ManagedBlock::Intact
+        }
+        Some(_) => ManagedBlock::Edited,
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Plain SHA-256 (FIPS 180-4), enough for change detection without pulling
+/// in a crypto dependency.
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut out = [0u8; 32];
+    for (bytes, word) in out.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+
+use crate::{
+    FieldInfo, FieldKind, RenderOptions, TablePath, TomlComment, check_depth, fmt_key, push_gap,
+    push_header,
+};
+
+/// A map at the root of a config renders each entry as a `[name]` section
+/// with the value type's comments, for plugin-style configs whose sections
+/// are only known at runtime.
+impl<K, V> TomlComment for BTreeMap<K, V>
+where
+    K: AsRef<str> + serde::Serialize,
+    V: TomlComment,
+{
+    fn _render_docs(&self, _out: &mut String, _opts: &RenderOptions) {}
+
+    fn _render(&self, out: &mut String, prefix: TablePath, opts: &RenderOptions) {
+        render(self.iter(), out, prefix, opts);
+    }
+
+    fn _fields(&self, out: &mut Vec<FieldInfo>, prefix: &str) {
+        fields(self.iter(), out, prefix);
+    }
+
+    fn _render_section(
+        &self,
+        out: &mut String,
+        prefix: TablePath,
+        path: &str,
+        opts: &RenderOptions,
+    ) -> bool {
+        render_section(self.iter(), out, prefix, path, opts)
+    }
+
+    fn _size_hint(&self) -> usize {
+        size_hint(self.iter())
+    }
+}
+
+/// Like the `BTreeMap` impl, with the sections sorted by name since a
+/// `HashMap` has no stable order.
+impl<K, V, S> TomlComment for HashMap<K, V, S>
+where
+    K: AsRef<str> + serde::Serialize,
+    V: TomlComment,
+    S: BuildHasher,
+{
+    fn _render_docs(&self, _out: &mut String, _opts: &RenderOptions) {}
+
+    fn _render(&self, out: &mut String, prefix: TablePath, opts: &RenderOptions) {
+        render(sorted(self), out, prefix, opts);
+    }
+
+    fn _fields(&self, out: &mut Vec<FieldInfo>, prefix: &str) {
+        fields(sorted(self), out, prefix);
+    }
+
+    fn _render_section(
+        &self,
+        out: &mut String,
+        prefix: TablePath,
+        path: &str,
+        opts: &RenderOptions,
+    ) -> bool {
+        render_section(self.iter(), out, prefix, path, opts)
+    }
+
+    fn _size_hint(&self) -> usize {
+        size_hint(self.iter())
+    }
+}
+
+fn sorted<K: AsRef<str>, V, S>(map: &HashMap<K, V, S>) -> Vec<(&K, &V)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+    entries
+}
+
+fn render<'a, K, V>(
+    entries: impl IntoIterator<Item = (&'a K, &'a V)>,
+    out: &mut String,
+    prefix: TablePath,
+    opts: &RenderOptions,
+) where
+    K: AsRef<str> + 'a,
+    V: TomlComment + 'a,
+{
+    for (i, (name, value)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            push_gap(out, opts);
+        }
+        render_entry(name.as_ref(), value, out, prefix, opts);
+    }
+}
+
+fn render_entry<V: TomlComment>(
+    name: &str,
+    value: &V,
+    out: &mut String,
+    prefix: TablePath,
+    opts: &RenderOptions,
+) {
+    let key = fmt_key(name);
+    let section = prefix.join(&key);
+    if !check_depth(opts, section) {
+        return;
+    }
+    push_header(out, opts, section, false);
+    let opts = &RenderOptions {
+        key_prefix: "",
+        in_table: true,
+        ..*opts
+    };
+    value._render_docs(out, opts);
+    value._render(out, section, opts);
+}
+
+fn render_section<'a, K, V>(
+    entries: impl IntoIterator<Item = (&'a K, &'a V)>,
+    out: &mut String,
+    prefix: TablePath,
+    path: &str,
+    opts: &RenderOptions,
+) -> bool
+where
+    K: AsRef<str> + 'a,
+    V: TomlComment + 'a,
+{
+    for (name, value) in entries {
+        let name = name.as_ref();
+        if path == name {
+            render_entry(name, value, out, prefix, opts);
+            return true;
+        }
+        if let Some(rest) = path
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('.'))
+        {
+            let key = fmt_key(name);
+            return value._render_section(out, prefix.join(&key), rest, opts);
+        }
+    }
+    false
+}
+
+fn fields<'a, K, V>(
+    entries: impl IntoIterator<Item = (&'a K, &'a V)>,
+    out: &mut Vec<FieldInfo>,
+    prefix: &str,
+) where
+    K: AsRef<str> + 'a,
+    V: TomlComment + 'a,
+{
+    for (name, value) in entries {
+        let path = if prefix.is_empty() {
+            name.as_ref().to_string()
+        } else {
+            format!("{prefix}.{}", name.as_ref())
+        };
+        out.push(FieldInfo {
+            path: path.clone(),
+            ty: V::_type_name(),
+            kind: FieldKind::Section,
+            docs: &[],
+            value: None,
+            env: None,
+            optional: false,
+            unit: None,
+            secret: false,
+            min: None,
+            max: None,
+            choices: &[],
+            example: None,
+            notes: &[],
+            aliases: &[],
+            requires: &[],
+            conflicts_with: &[],
+        });
+        value._fields(out, &path);
+    }
+}
+
+fn size_hint<'a, K, V>(entries: impl IntoIterator<Item = (&'a K, &'a V)>) -> usize
+where
+    K: AsRef<str> + 'a,
+    V: TomlComment + 'a,
+{
+    entries
+        .into_iter()
+        .map(|(name, value)| name.as_ref().len() + 4 + value._size_hint())
+        .sum()
+}
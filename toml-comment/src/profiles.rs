@@ -0,0 +1,155 @@
+use crate::load::{LoadOptions, Loader};
+use crate::{
+    FieldInfo, FieldKind, NumberFormat, RenderOptions, TablePath, TomlComment, fmt_key, push_docs,
+    push_entry, push_gap, push_header,
+};
+
+/// Cargo-style profiles: one `[default]` table with every key and its
+/// comments, then a `[profile.<name>]` table per profile holding only the
+/// keys that differ from it. [`Profiles::load`] reads one profile back.
+#[derive(Debug, Clone)]
+pub struct Profiles<T> {
+    pub default: T,
+    /// In the order they're written.
+    pub profiles: Vec<(String, T)>,
+}
+
+impl<T: TomlComment> Profiles<T> {
+    pub fn new(default: T) -> Self {
+        Self {
+            default,
+            profiles: Vec::new(),
+        }
+    }
+
+    pub fn profile(mut self, name: impl Into<String>, config: T) -> Self {
+        self.profiles.push((name.into(), config));
+        self
+    }
+
+    /// Keys inside `[[table]]` arrays are only written under `[default]`;
+    /// a profile can't override them.
+    pub fn to_commented_toml(&self) -> String {
+        let opts = T::render_options();
+        let mut out = String::new();
+        self.default._render_docs(&mut out, &opts);
+        if !out.is_empty() {
+            push_gap(&mut out, &opts);
+        }
+        push_header(&mut out, &opts, "default", false);
+        let opts = RenderOptions {
+            in_table: true,
+            ..opts
+        };
+        self.default
+            ._render(&mut out, TablePath::ROOT.join("default"), &opts);
+
+        let defaults = self.default.fields();
+        for (name, config) in &self.profiles {
+            let profile = format!("profile.{}", fmt_key(name));
+            push_gap(&mut out, &opts);
+            push_header(&mut out, &opts, &profile, false);
+            let fields = config.fields();
+            let mut changed: Vec<(&FieldInfo, &toml::Value)> = fields
+                .iter()
+                .filter(|field| {
+                    matches!(field.kind, FieldKind::Value | FieldKind::Map)
+                        && !field.path.contains('[')
+                })
+                .filter_map(|field| Some((field, field.value.as_ref()?)))
+                .filter(|(field, value)| {
+                    let default = defaults.iter().find(|d| d.path == field.path);
+                    !default.is_some_and(|d| d.value.as_ref() == Some(*value))
+                })
+                .collect();
+            // Keys of one table must sit together under its header, and
+            // top-level keys before any header at all.
+            let mut sections: Vec<&str> = vec![""];
+            for (field, _) in &changed {
+                if !sections.contains(&field.section()) {
+                    sections.push(field.section());
+                }
+            }
+            changed.sort_by_key(|(field, _)| sections.iter().position(|s| *s == field.section()));
+            let mut section = "";
+            for (field, value) in changed {
+                if field.section() != section {
+                    section = field.section();
+                    push_gap(&mut out, &opts);
+                    push_header(&mut out, &opts, format_args!("{profile}.{section}"), false);
+                }
+                let docs = field.expanded_docs();
+                let docs: Vec<&str> = docs.iter().map(String::as_str).collect();
+                push_docs(&mut out, &opts, None, &docs, &[]);
+                let key = &field.path[field.section().len()..].trim_start_matches('.');
+                push_entry(&mut out, key, value, &NumberFormat::DEFAULT, &opts);
+            }
+        }
+        out
+    }
+}
+
+impl<T: serde::de::DeserializeOwned> Profiles<T> {
+    /// Reads profile `name` from a profiles file: its keys over those in
+    /// `[default]`, tables merged key by key.
+    ///
+    /// Keys `T` has no field for are passed to serde with the rest, which
+    /// skips them unless `T` has `#[serde(deny_unknown_fields)]`. Use
+    /// [`Profiles::load_with`] with
+    /// [`UnknownKeys::Deny`](crate::UnknownKeys::Deny) to reject them.
+    pub fn load(text: &str, name: &str) -> Result<T, toml::de::Error> {
+        toml::Value::Table(merged(text, name, |_| {})?).try_into()
+    }
+}
+
+impl<T: TomlComment + Default + serde::de::DeserializeOwned> Profiles<T> {
+    /// [`Profiles::load`] with [`LoadOptions`]. `[default]` and the
+    /// profile's table are each matched against the config before merging.
+    pub fn load_with(text: &str, name: &str, opts: &LoadOptions) -> Result<T, toml::de::Error> {
+        let loader = Loader::new::<T>(opts);
+        loader.finish(merged(text, name, |table| loader.prepare(table))?)
+    }
+}
+
+/// `[profile.<name>]` merged over `[default]`, each passed through
+/// `prepare` first.
+fn merged(
+    text: &str,
+    name: &str,
+    prepare: impl Fn(&mut toml::Table),
+) -> Result<toml::Table, toml::de::Error> {
+    let mut doc: toml::Table = text.parse()?;
+    let mut merged = match doc.remove("default") {
+        Some(toml::Value::Table(table)) => table,
+        _ => toml::Table::new(),
+    };
+    prepare(&mut merged);
+    let overrides = doc
+        .get_mut("profile")
+        .and_then(toml::Value::as_table_mut)
+        .and_then(|profiles| profiles.remove(name));
+    match overrides {
+        Some(toml::Value::Table(mut overrides)) => {
+            prepare(&mut overrides);
+            merge(&mut merged, overrides);
+        }
+        _ => {
+            return Err(serde::de::Error::custom(format!(
+                "no [profile.{name}] table"
+            )));
+        }
+    }
+    Ok(merged)
+}
+
+/// Overlays `overrides` on `base`, merging tables that appear in both.
+pub(crate) fn merge(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(value)) => merge(base, value),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
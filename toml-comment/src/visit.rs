@@ -0,0 +1,111 @@
+use crate::{FieldInfo, FieldKind};
+
+/// Receives a config's structure in render order, for output formats other
+/// than TOML. Keys are relative to the enclosing section; each key's and
+/// section's doc lines arrive through `on_comment` just before it.
+///
+/// The walk is driven by [`TomlComment::fields`](crate::TomlComment::fields),
+/// not by the TOML renderer, so it sees what a [`FieldInfo`] carries: doc
+/// comments, the `example` line and notes like `unit:` and `see:`. Lines
+/// only the renderer produces are not visited: `doc_fn` output, `i18n_key`
+/// lookups, `show_unset` placeholders and where `section_docs` puts a
+/// section's comments.
+pub trait RenderVisitor {
+    /// One doc line, trimmed, with placeholders filled in. Empty lines are
+    /// paragraph breaks.
+    fn on_comment(&mut self, _line: &str) {}
+
+    /// A key holding a value. `field.value` is `None` for an unset
+    /// `Option`.
+    fn on_key_value(&mut self, key: &str, field: &FieldInfo);
+
+    /// A `[section]`, or a `[[table]]` array when `field.kind` is
+    /// [`FieldKind::Tables`]; its contents follow until the matching
+    /// `on_section_end`.
+    fn on_section_start(&mut self, _key: &str, _field: &FieldInfo) {}
+
+    fn on_section_end(&mut self, _key: &str) {}
+
+    /// One element of a `[[table]]` array, between its section events.
+    fn on_element_start(&mut self, _index: usize) {}
+
+    fn on_element_end(&mut self, _index: usize) {}
+}
+
+enum Open<'a> {
+    Section { path: &'a str, key: &'a str },
+    Element { path: &'a str, index: usize },
+}
+
+impl Open<'_> {
+    fn path(&self) -> &str {
+        match self {
+            Open::Section { path, .. } | Open::Element { path, .. } => path,
+        }
+    }
+
+    fn close(&self, visitor: &mut dyn RenderVisitor) {
+        match self {
+            Open::Section { key, .. } => visitor.on_section_end(key),
+            Open::Element { index, .. } => visitor.on_element_end(*index),
+        }
+    }
+}
+
+pub(crate) fn walk(fields: &[FieldInfo], visitor: &mut dyn RenderVisitor) {
+    let mut open: Vec<Open> = Vec::new();
+    for field in fields {
+        let path = field.path.as_str();
+        let parent = match path.rfind('.') {
+            Some(dot) => &path[..dot],
+            None => "",
+        };
+        while let Some(top) = open.last() {
+            let top = top.path();
+            if parent == top
+                || parent.starts_with(&format!("{top}."))
+                || parent.starts_with(&format!("{top}["))
+            {
+                break;
+            }
+            open.pop().expect("non-empty").close(visitor);
+        }
+        // `upstream[1].addr` opens element 1 of `upstream`.
+        if parent.ends_with(']') && open.last().map(Open::path) != Some(parent) {
+            let bracket = parent.rfind('[').expect("indexed path");
+            let index = parent[bracket + 1..parent.len() - 1]
+                .parse()
+                .expect("numeric index");
+            visitor.on_element_start(index);
+            open.push(Open::Element {
+                path: parent,
+                index,
+            });
+        }
+
+        let key = if parent.is_empty() {
+            path
+        } else {
+            &path[parent.len() + 1..]
+        };
+        for line in field.expanded_docs() {
+            visitor.on_comment(line.trim());
+        }
+        if let Some(example) = &field.example {
+            visitor.on_comment(example);
+        }
+        for note in field.notes {
+            visitor.on_comment(note);
+        }
+        match field.kind {
+            FieldKind::Section | FieldKind::Tables => {
+                visitor.on_section_start(key, field);
+                open.push(Open::Section { path, key });
+            }
+            FieldKind::Value | FieldKind::Map => visitor.on_key_value(key, field),
+        }
+    }
+    while let Some(top) = open.pop() {
+        top.close(visitor);
+    }
+}
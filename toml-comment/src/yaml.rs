@@ -0,0 +1,143 @@
+use crate::{FieldInfo, FieldKind, RenderVisitor, json};
+
+/// Block-style YAML with the doc comments as `#` lines. Strings are always
+/// double-quoted so values like `no` or `1.0` keep their type.
+#[derive(Default)]
+pub(crate) struct YamlWriter {
+    pub(crate) out: String,
+    indent: usize,
+    /// A `key:` line held back until we know whether the section is empty.
+    pending: Option<(String, &'static str)>,
+}
+
+impl YamlWriter {
+    fn flush(&mut self) {
+        if let Some((line, _)) = self.pending.take() {
+            self.out.push_str(&line);
+            self.out.push('\n');
+        }
+    }
+
+    fn pad(&self) -> String {
+        " ".repeat(self.indent)
+    }
+
+    fn push_table(&mut self, table: &toml::Table) {
+        for (key, value) in table {
+            let pad = self.pad();
+            match value {
+                toml::Value::Table(inner) if !inner.is_empty() => {
+                    self.out.push_str(&format!("{pad}{}:\n", key_yaml(key)));
+                    self.indent += 2;
+                    self.push_table(inner);
+                    self.indent -= 2;
+                }
+                _ => self
+                    .out
+                    .push_str(&format!("{pad}{}: {}\n", key_yaml(key), value_yaml(value))),
+            }
+        }
+    }
+}
+
+impl RenderVisitor for YamlWriter {
+    fn on_comment(&mut self, line: &str) {
+        self.flush();
+        if line.is_empty() {
+            self.out.push_str(&format!("{}#\n", self.pad()));
+        } else {
+            self.out.push_str(&format!("{}# {line}\n", self.pad()));
+        }
+    }
+
+    fn on_key_value(&mut self, key: &str, field: &FieldInfo) {
+        self.flush();
+        let pad = self.pad();
+        match &field.value {
+            Some(toml::Value::Table(table)) if !table.is_empty() => {
+                self.out.push_str(&format!("{pad}{}:\n", key_yaml(key)));
+                self.indent += 2;
+                self.push_table(table);
+                self.indent -= 2;
+            }
+            Some(value) => {
+                self.out
+                    .push_str(&format!("{pad}{}: {}\n", key_yaml(key), value_yaml(value)));
+            }
+            None => self
+                .out
+                .push_str(&format!("{pad}{}: null\n", key_yaml(key))),
+        }
+    }
+
+    fn on_section_start(&mut self, key: &str, field: &FieldInfo) {
+        self.flush();
+        let empty = if field.kind == FieldKind::Tables {
+            "[]"
+        } else {
+            "{}"
+        };
+        self.pending = Some((format!("{}{}:", self.pad(), key_yaml(key)), empty));
+        self.indent += 2;
+    }
+
+    fn on_section_end(&mut self, _key: &str) {
+        if let Some((line, empty)) = self.pending.take() {
+            self.out.push_str(&format!("{line} {empty}\n"));
+        }
+        self.indent -= 2;
+    }
+
+    fn on_element_start(&mut self, _index: usize) {
+        self.flush();
+        self.out.push_str(&format!("{}-\n", self.pad()));
+        self.indent += 2;
+    }
+
+    fn on_element_end(&mut self, _index: usize) {
+        self.indent -= 2;
+    }
+}
+
+/// Keys YAML would read as something other than a string are quoted.
+pub(crate) fn key_yaml(key: &str) -> String {
+    let plain = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.')
+        && !matches!(
+            key.to_ascii_lowercase().as_str(),
+            "true" | "false" | "null" | "yes" | "no" | "on" | "off" | "y" | "n"
+        )
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if plain {
+        key.to_string()
+    } else {
+        json::string(key)
+    }
+}
+
+fn value_yaml(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => json::string(s),
+        toml::Value::Integer(n) => n.to_string(),
+        toml::Value::Float(f) if f.is_nan() => ".nan".to_string(),
+        toml::Value::Float(f) if f.is_infinite() => {
+            if *f > 0.0 { ".inf" } else { "-.inf" }.to_string()
+        }
+        toml::Value::Float(f) => format!("{f:?}"),
+        toml::Value::Boolean(b) => b.to_string(),
+        toml::Value::Datetime(dt) => json::string(&dt.to_string()),
+        toml::Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(value_yaml).collect();
+            format!("[{}]", items.join(", "))
+        }
+        toml::Value::Table(table) => {
+            let entries: Vec<String> = table
+                .iter()
+                .map(|(k, v)| format!("{}: {}", key_yaml(k), value_yaml(v)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+    }
+}
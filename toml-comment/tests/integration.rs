@@ -1,6 +1,10 @@
-use serde::Serialize;
-use std::collections::{BTreeMap, HashMap};
-use toml_comment::TomlComment;
+// Several fixtures keep hand-written `Default` impls on purpose, to cover
+// types whose defaults don't come from a derive.
+#![allow(clippy::derivable_impls)]
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
+use toml_comment::{RenderedLine, TomlComment};
 
 /// Application settings
 #[derive(Serialize, TomlComment)]
@@ -127,6 +131,49 @@ extra = \"world\"
     assert_eq!(toml, expected);
 }
 
+#[test]
+fn option_none_shown_unset() {
+    let opts = toml_comment::RenderOptions {
+        show_unset: true,
+        ..Default::default()
+    };
+    let expected = "\
+# Always present
+name = \"hello\"
+# Sometimes present
+# extra = <string>
+";
+    assert_eq!(
+        WithOption::default().to_commented_toml_with(&opts),
+        expected
+    );
+    assert_eq!(
+        WithOptionalEnum::default().to_commented_toml_with(&opts),
+        "# level = <value>\n"
+    );
+}
+
+#[derive(Default, Serialize, TomlComment)]
+struct WithPlaceholders {
+    /// Outgoing mail server
+    #[toml_comment(placeholder = "\"smtp.example.com\"")]
+    smtp_host: Option<String>,
+    /// Extra recipients
+    #[toml_comment(placeholder = "[\"ops@example.com\"]")]
+    cc: Option<Vec<String>>,
+}
+
+#[test]
+fn option_placeholders() {
+    let expected = "\
+# Outgoing mail server
+# smtp_host = \"smtp.example.com\"
+# Extra recipients
+# cc = [\"ops@example.com\"]
+";
+    assert_eq!(WithPlaceholders::default_toml(), expected);
+}
+
 #[derive(Serialize, TomlComment)]
 struct WithVec {
     /// Allowed origins
@@ -465,3 +512,4318 @@ env = \"prod\"
 ";
     assert_eq!(toml, expected);
 }
+
+// --- .env.example ---
+
+#[derive(Serialize, TomlComment)]
+struct EnvServer {
+    /// Port to listen on
+    #[toml_comment(env = "APP_PORT")]
+    port: u16,
+    /// Bind address
+    #[toml_comment(env = "APP_HOST")]
+    host: String,
+    /// Not exposed through the environment
+    workers: u32,
+}
+
+impl Default for EnvServer {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            host: "127.0.0.1".to_string(),
+            workers: 4,
+        }
+    }
+}
+
+#[derive(Serialize, TomlComment)]
+struct EnvRoot {
+    /// Greeting shown on startup
+    #[toml_comment(env = "APP_MOTD")]
+    motd: String,
+    /// Optional API token
+    #[toml_comment(env = "APP_TOKEN")]
+    token: Option<String>,
+    server: EnvServer,
+}
+
+impl Default for EnvRoot {
+    fn default() -> Self {
+        Self {
+            motd: "hello world".to_string(),
+            token: None,
+            server: EnvServer::default(),
+        }
+    }
+}
+
+#[test]
+fn env_example() {
+    let env = EnvRoot::default().to_env_example();
+    let expected = "\
+# Greeting shown on startup
+APP_MOTD=\"hello world\"
+# Optional API token
+APP_TOKEN=
+# Port to listen on
+APP_PORT=8080
+# Bind address
+APP_HOST=127.0.0.1
+";
+    assert_eq!(env, expected);
+}
+
+#[test]
+fn env_attr_does_not_change_toml() {
+    let toml = EnvServer::default_toml();
+    assert!(toml.contains("port = 8080"));
+    assert!(!toml.contains("APP_PORT"));
+}
+
+// --- Metadata ---
+
+#[test]
+fn fields_metadata() {
+    let fields = RootConfig::default().fields();
+    let paths: Vec<_> = fields.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["server", "server.port", "server.host"]);
+    assert_eq!(fields[0].kind, toml_comment::FieldKind::Section);
+    assert_eq!(fields[1].ty, "u16");
+    assert_eq!(fields[1].docs, [" Port to listen on"]);
+    assert_eq!(fields[1].value, Some(toml::Value::Integer(8080)));
+}
+
+#[derive(Serialize, Deserialize, toml_comment::TypeDocs)]
+#[serde(rename_all = "lowercase")]
+enum Theme {
+    Light,
+    Dark,
+    #[serde(rename = "system")]
+    FollowSystem,
+}
+
+#[derive(Serialize, TomlComment)]
+struct UiSettings {
+    /// Color theme
+    #[toml_comment(choices)]
+    theme: Theme,
+    /// Font size in points
+    #[toml_comment(min = 6, max = 72.5)]
+    font_size: u32,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::Light,
+            font_size: 12,
+        }
+    }
+}
+
+#[derive(Default, Serialize, TomlComment)]
+struct EditorSettings {
+    /// Sync service token
+    #[toml_comment(secret)]
+    token: String,
+    ui: UiSettings,
+}
+
+#[test]
+fn editor_metadata() {
+    let fields = EditorSettings::default().fields();
+    assert!(fields[0].secret);
+    assert_eq!(fields[0].section(), "");
+    assert_eq!(fields[2].path, "ui.theme");
+    assert_eq!(fields[2].section(), "ui");
+    assert_eq!(fields[2].choices, ["light", "dark", "system"]);
+    assert_eq!(fields[3].min, Some(6.0));
+    assert_eq!(fields[3].max, Some(72.5));
+    assert!(!fields[3].secret && fields[3].choices.is_empty());
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithThemeCycle {
+    /// Themes to cycle through
+    #[toml_comment(choices)]
+    themes: Vec<Theme>,
+}
+
+impl Default for WithThemeCycle {
+    fn default() -> Self {
+        Self {
+            themes: vec![Theme::Dark, Theme::FollowSystem],
+        }
+    }
+}
+
+#[test]
+fn enum_array_choices() {
+    let cfg = WithThemeCycle::default();
+    let expected = "\
+# Themes to cycle through
+themes = [\"dark\", \"system\"]
+";
+    assert_eq!(cfg.to_commented_toml(), expected);
+    assert_eq!(cfg.fields()[0].choices, ["light", "dark", "system"]);
+
+    let opts = toml_comment::RenderOptions {
+        list_choices: true,
+        ..Default::default()
+    };
+    let expected = "\
+# Themes to cycle through
+# one of: \"light\", \"dark\", \"system\"
+themes = [\"dark\", \"system\"]
+";
+    assert_eq!(cfg.to_commented_toml_with(&opts), expected);
+}
+
+#[test]
+fn key_paths() {
+    let paths: Vec<_> = EditorSettings::key_paths().collect();
+    assert_eq!(
+        paths.iter().map(|k| k.path.as_str()).collect::<Vec<_>>(),
+        ["token", "ui.theme", "ui.font_size"]
+    );
+    assert_eq!(paths[2].ty, "u32");
+    assert!(
+        EnvRoot::key_paths().any(|k| k.path == "token" && k.ty == "Option<String>" && k.optional)
+    );
+}
+
+#[test]
+fn schema_json() {
+    let json = EditorSettings::default().schema_json();
+    let expected = r#"{
+  "token": {
+    "type": "String",
+    "kind": "value",
+    "docs": ["Sync service token"],
+    "default": "",
+    "optional": false,
+    "secret": true
+  },
+  "ui": {
+    "type": "UiSettings",
+    "kind": "section",
+    "docs": [],
+    "optional": false
+  },
+  "ui.theme": {
+    "type": "Theme",
+    "kind": "value",
+    "docs": ["Color theme"],
+    "default": "light",
+    "optional": false,
+    "choices": ["light", "dark", "system"]
+  },
+  "ui.font_size": {
+    "type": "u32",
+    "kind": "value",
+    "docs": ["Font size in points"],
+    "default": 12,
+    "optional": false,
+    "min": 6.0,
+    "max": 72.5
+  }
+}
+"#;
+    assert_eq!(json, expected);
+}
+
+#[test]
+fn man_page() {
+    let man = EnvRoot::default().to_man_page();
+    let expected = "\
+.SH CONFIGURATION
+.TP
+\\fBmotd\\fR (\\fIString\\fR, default: \\fB\"hello world\"\\fR)
+Greeting shown on startup
+.TP
+\\fBtoken\\fR (\\fIOption<String>\\fR, default: unset)
+Optional API token
+.SS [server]
+.TP
+\\fBserver.port\\fR (\\fIu16\\fR, default: \\fB8080\\fR)
+Port to listen on
+.TP
+\\fBserver.host\\fR (\\fIString\\fR, default: \\fB\"127.0.0.1\"\\fR)
+Bind address
+.TP
+\\fBserver.workers\\fR (\\fIu32\\fR, default: \\fB4\\fR)
+Not exposed through the environment
+";
+    assert_eq!(man, expected);
+}
+
+#[test]
+fn html_docs() {
+    let html = EnvRoot::default().to_html_docs();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<h2 id=\"server\"><a href=\"#server\">[server]</a></h2>"));
+    assert!(html.contains(
+        "<a href=\"#server.port\">server.port</a> <span class=\"type\">u16</span>\n\
+         <p>Port to listen on</p>\n\
+         <p class=\"default\">Default: <code>8080</code></p>"
+    ));
+    assert!(html.contains("<span class=\"type\">Option&lt;String&gt;</span>"));
+    assert!(html.contains("Default: <code>&quot;hello world&quot;</code>"));
+    assert!(html.contains("Default: unset"));
+}
+
+#[test]
+fn config_map() {
+    let yaml = MultiSection::default().to_config_map("myapp", "config.toml");
+    let expected = "\
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: myapp
+data:
+  config.toml: |-
+    # Multi-section config
+
+    [logging]
+    # Log level
+    level = \"info\"
+
+    [database]
+    # Connection URL
+    url = \"sqlite://data.db\"
+";
+    assert_eq!(yaml, expected);
+}
+
+#[test]
+fn config_map_empty() {
+    let yaml = WithEmptyMap::default().to_config_map("empty", "config.toml");
+    assert!(yaml.ends_with("data:\n  config.toml: \"\"\n"));
+}
+
+#[test]
+fn config_map_quoted_names() {
+    let yaml = MultiSection::default().to_config_map("1024", "app:config.toml");
+    assert!(yaml.contains("  name: \"1024\"\ndata:\n  \"app:config.toml\": |-\n"));
+    let yaml = MultiSection::default().to_config_map("yes", ".hidden.toml");
+    assert!(yaml.contains("  name: \"yes\"\ndata:\n  \".hidden.toml\": |-\n"));
+}
+
+// --- Localization ---
+
+/// Server settings
+#[derive(Serialize, TomlComment)]
+#[toml_comment(i18n_key = "server")]
+struct LocalizedServer {
+    /// Port to listen on
+    #[toml_comment(i18n_key = "server.port")]
+    port: u16,
+    /// Bind address
+    #[toml_comment(i18n_key = "server.host")]
+    host: String,
+}
+
+impl Default for LocalizedServer {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            host: "127.0.0.1".to_string(),
+        }
+    }
+}
+
+fn german(key: &str) -> Option<String> {
+    match key {
+        "server" => Some("Servereinstellungen".to_string()),
+        "server.port" => Some("Port, auf dem gelauscht wird".to_string()),
+        _ => None,
+    }
+}
+
+#[test]
+fn localized_comments() {
+    let toml = LocalizedServer::default().to_commented_toml_localized(&german);
+    let expected = "\
+# Servereinstellungen
+# Port, auf dem gelauscht wird
+port = 8080
+# Bind address
+host = \"127.0.0.1\"
+";
+    assert_eq!(toml, expected);
+}
+
+#[test]
+fn localized_keys_ignored_without_localizer() {
+    let toml = LocalizedServer::default_toml();
+    assert!(toml.starts_with("# Server settings\n# Port to listen on\n"));
+}
+
+// --- Comment placeholders ---
+
+#[derive(Serialize, TomlComment)]
+struct Templated {
+    /// Port to listen on (default: {default})
+    /// Override with {env}, must fit in a {type}
+    #[toml_comment(env = "APP_PORT")]
+    port: u16,
+    /// Literal braces like {other} are left alone
+    name: String,
+}
+
+impl Default for Templated {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            name: "app".to_string(),
+        }
+    }
+}
+
+#[test]
+fn doc_placeholders() {
+    let cfg = Templated {
+        port: 9000,
+        ..Templated::default()
+    };
+    let expected = "\
+# Port to listen on (default: 8080)
+# Override with APP_PORT, must fit in a u16
+port = 9000
+# Literal braces like {other} are left alone
+name = \"app\"
+";
+    assert_eq!(cfg.to_commented_toml(), expected);
+}
+
+#[test]
+fn doc_placeholders_in_metadata() {
+    let fields = Templated::default().fields();
+    assert_eq!(
+        fields[0].expanded_docs(),
+        [
+            " Port to listen on (default: 8080)",
+            " Override with APP_PORT, must fit in a u16"
+        ]
+    );
+}
+
+/// Built by a builder, so there is no `Default`
+#[derive(Serialize, TomlComment)]
+struct BuiltConfig {
+    /// Port to listen on (default: {default})
+    port: u16,
+}
+
+#[test]
+fn render_without_default() {
+    let cfg = BuiltConfig { port: 9000 };
+    let expected = "\
+# Built by a builder, so there is no `Default`
+# Port to listen on (default: unset)
+port = 9000
+";
+    assert_eq!(cfg.to_commented_toml(), expected);
+}
+
+// --- Markdown in comments ---
+
+#[derive(Default, Serialize, TomlComment)]
+struct Markdowny {
+    /// Set to `true` to enable [`Tracing`], see [the guide](https://example.com/guide)
+    /// or [the reference][ref] for **details**
+    tracing: bool,
+}
+
+#[test]
+fn markdown_kept_by_default() {
+    let toml = Markdowny::default_toml();
+    assert!(toml.starts_with("# Set to `true` to enable [`Tracing`]"));
+}
+
+#[test]
+fn markdown_stripped() {
+    let opts = toml_comment::RenderOptions {
+        strip_markdown: true,
+        ..Default::default()
+    };
+    let toml = Markdowny::default().to_commented_toml_with(&opts);
+    let expected = "\
+# Set to true to enable Tracing, see the guide (https://example.com/guide)
+# or the reference for details
+tracing = false
+";
+    assert_eq!(toml, expected);
+}
+
+#[derive(Default, Serialize, TomlComment)]
+struct MarkdownEmphasis {
+    /// Listen on *all* interfaces or _only_ [`Config::host`], given as
+    /// [a, b] or `[c]`, for **both** max_connections and 2 * 3 * 4
+    listen: bool,
+    /// See the [server] table, [crate::Options] and [reload()]
+    reload: bool,
+}
+
+#[test]
+fn markdown_stripped_emphasis_and_brackets() {
+    let opts = toml_comment::RenderOptions {
+        strip_markdown: true,
+        ..Default::default()
+    };
+    let toml = MarkdownEmphasis::default().to_commented_toml_with(&opts);
+    let expected = "\
+# Listen on all interfaces or only Config::host, given as
+# [a, b] or [c], for both max_connections and 2 * 3 * 4
+listen = false
+# See the [server] table, crate::Options and reload()
+reload = false
+";
+    assert_eq!(toml, expected);
+}
+
+#[derive(Default, Serialize, TomlComment)]
+struct WithExample {
+    /// Upstream servers, for `example`:
+    ///
+    /// ```toml
+    /// upstreams = ["a:80", "b:80"]
+    /// ```
+    upstreams: Vec<String>,
+}
+
+#[test]
+fn fenced_code_block_kept() {
+    let expected = "\
+# Upstream servers, for `example`:
+#
+# ```toml
+# upstreams = [\"a:80\", \"b:80\"]
+# ```
+upstreams = []
+";
+    assert_eq!(WithExample::default_toml(), expected);
+}
+
+#[test]
+fn fenced_code_block_indented_when_stripping_markdown() {
+    let opts = toml_comment::RenderOptions {
+        strip_markdown: true,
+        ..Default::default()
+    };
+    let expected = "\
+# Upstream servers, for example:
+#
+#     upstreams = [\"a:80\", \"b:80\"]
+upstreams = []
+";
+    assert_eq!(
+        WithExample::default().to_commented_toml_with(&opts),
+        expected
+    );
+}
+
+#[derive(Default, Serialize, TomlComment)]
+struct MessyDocs {
+    /// First paragraph.
+    ///
+    ///
+    /// Accepted values:
+    /// * `fast`
+    /// + `safe`
+    ///   (the default)
+    ///
+    mode: String,
+    #[doc = "Written without a leading space"]
+    /**
+     Block doc comment
+     spanning lines
+    */
+    level: u8,
+}
+
+#[test]
+fn doc_comment_normalization() {
+    let expected = "\
+# First paragraph.
+#
+# Accepted values:
+# - `fast`
+# - `safe`
+#   (the default)
+mode = \"\"
+# Written without a leading space
+# Block doc comment
+# spanning lines
+level = 0
+";
+    assert_eq!(MessyDocs::default_toml(), expected);
+}
+
+#[derive(Default, Serialize, TomlComment)]
+struct WideSpaceDocs {
+    /// 表示名
+    ///　全角スペースで字下げ
+    #[doc = "\u{a0}no-break space"]
+    title: String,
+}
+
+#[test]
+fn doc_comment_multibyte_indent() {
+    let expected = "\
+# 表示名
+# 全角スペースで字下げ
+# no-break space
+title = \"\"
+";
+    assert_eq!(WideSpaceDocs::default_toml(), expected);
+}
+
+// --- Section doc placement ---
+
+/// Connection pool tuning
+#[derive(Serialize, TomlComment)]
+struct PoolConfig {
+    /// Maximum open connections
+    size: u32,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self { size: 8 }
+    }
+}
+
+#[derive(Default, Serialize, TomlComment)]
+struct SectionDocPlacement {
+    /// Primary pool
+    primary: PoolConfig,
+    /// Replica pool
+    #[toml_comment(section_docs = "above")]
+    replica: PoolConfig,
+    /// Analytics pool
+    #[toml_comment(section_docs = "hidden")]
+    analytics: PoolConfig,
+}
+
+#[test]
+fn section_doc_placement() {
+    let expected = "\
+# Primary pool
+[primary]
+# Connection pool tuning
+# Maximum open connections
+size = 8
+
+# Connection pool tuning
+# Replica pool
+[replica]
+# Maximum open connections
+size = 8
+
+# Analytics pool
+[analytics]
+# Maximum open connections
+size = 8
+";
+    assert_eq!(SectionDocPlacement::default_toml(), expected);
+}
+
+// --- Type doc fallback ---
+
+/// Request timeout in seconds
+#[derive(Serialize, toml_comment::TypeDocs)]
+struct Seconds(u64);
+
+#[derive(Serialize, TomlComment)]
+struct WithTypeDocs {
+    #[toml_comment(inline, type_docs)]
+    read_timeout: Seconds,
+    /// Own docs win over the type's
+    #[toml_comment(inline, type_docs)]
+    write_timeout: Seconds,
+    #[toml_comment(type_docs)]
+    idle_timeout: Option<Seconds>,
+    #[toml_comment(inline)]
+    connect_timeout: Seconds,
+}
+
+impl Default for WithTypeDocs {
+    fn default() -> Self {
+        Self {
+            read_timeout: Seconds(30),
+            write_timeout: Seconds(10),
+            idle_timeout: Some(Seconds(60)),
+            connect_timeout: Seconds(5),
+        }
+    }
+}
+
+#[test]
+fn type_docs_fallback() {
+    let expected = "\
+# Request timeout in seconds
+read_timeout = 30
+# Own docs win over the type's
+write_timeout = 10
+# Request timeout in seconds
+idle_timeout = 60
+connect_timeout = 5
+";
+    assert_eq!(WithTypeDocs::default_toml(), expected);
+    assert_eq!(
+        WithTypeDocs::default().fields()[0].docs,
+        [" Request timeout in seconds"]
+    );
+}
+
+// --- Required docs ---
+
+#[derive(Serialize, TomlComment, Default)]
+#[toml_comment(deny_missing_docs)]
+struct FullyDocumented {
+    /// Listen port
+    port: u16,
+    #[toml_comment(type_docs)]
+    timeout: Option<Seconds>,
+    #[doc(hidden)]
+    internal: bool,
+}
+
+#[test]
+fn deny_missing_docs_accepts_documented() {
+    let toml = FullyDocumented::default_toml();
+    assert!(toml.starts_with("# Listen port\nport = 0\n"));
+}
+
+// --- #[doc(hidden)] fields ---
+
+#[derive(Serialize, TomlComment)]
+struct WithHiddenField {
+    /// Public knob
+    workers: u32,
+    /// Internal: only for the test harness
+    #[doc(hidden)]
+    fault_injection: bool,
+}
+
+impl Default for WithHiddenField {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            fault_injection: false,
+        }
+    }
+}
+
+#[test]
+fn doc_hidden_field_renders_without_comment() {
+    let expected = "\
+# Public knob
+workers = 4
+fault_injection = false
+";
+    assert_eq!(WithHiddenField::default_toml(), expected);
+    assert!(WithHiddenField::default().fields()[1].docs.is_empty());
+}
+
+#[test]
+fn doc_hidden_field_skipped() {
+    let opts = toml_comment::RenderOptions {
+        skip_hidden: true,
+        ..Default::default()
+    };
+    let toml = WithHiddenField::default().to_commented_toml_with(&opts);
+    assert_eq!(toml, "# Public knob\nworkers = 4\n");
+}
+
+// --- #[cfg] on fields ---
+
+#[derive(Serialize, TomlComment)]
+struct WithCfgFields {
+    /// Always there
+    name: String,
+    /// Always compiled in
+    #[cfg(test)]
+    metrics: bool,
+    /// Never compiled in
+    #[cfg(not(test))]
+    ghost: u8,
+}
+
+impl Default for WithCfgFields {
+    fn default() -> Self {
+        Self {
+            name: "app".to_string(),
+            metrics: true,
+        }
+    }
+}
+
+#[test]
+fn cfg_fields() {
+    let expected = "\
+# Always there
+name = \"app\"
+# Always compiled in
+metrics = true
+";
+    assert_eq!(WithCfgFields::default_toml(), expected);
+    assert_eq!(WithCfgFields::default().fields().len(), 2);
+}
+
+// --- Platform-specific sibling fields ---
+
+#[derive(Serialize, TomlComment)]
+struct PlatformPaths {
+    /// Where state is kept
+    #[cfg(unix)]
+    data_dir: String,
+    /// Where state is kept
+    #[cfg(windows)]
+    data_dir: String,
+    /// Shared by every platform
+    verbose: bool,
+}
+
+impl Default for PlatformPaths {
+    fn default() -> Self {
+        Self {
+            #[cfg(unix)]
+            data_dir: "/var/lib/app".to_string(),
+            #[cfg(windows)]
+            data_dir: "C:\\ProgramData\\app".to_string(),
+            verbose: false,
+        }
+    }
+}
+
+#[test]
+fn platform_sibling_fields() {
+    let toml = PlatformPaths::default_toml();
+    assert_eq!(toml.matches("data_dir = ").count(), 1);
+    #[cfg(unix)]
+    assert!(toml.starts_with("# Where state is kept\ndata_dir = \"/var/lib/app\"\n"));
+    assert!(toml.ends_with("# Shared by every platform\nverbose = false\n"));
+}
+
+// --- Marker fields ---
+
+#[derive(Serialize, TomlComment, Default)]
+struct WithMarker {
+    /// Worker count
+    workers: u32,
+    _unit: std::marker::PhantomData<u64>,
+    build: (),
+}
+
+#[test]
+fn marker_fields_skipped() {
+    let expected = "\
+# Worker count
+workers = 0
+";
+    let config = WithMarker::default();
+    assert_eq!(config.to_commented_toml(), expected);
+    assert_eq!(config.fields().len(), 1);
+}
+
+// --- Flatten ---
+
+#[derive(Serialize, TomlComment)]
+struct CommonTimeouts {
+    /// Connect timeout in seconds
+    connect: u32,
+    /// Read timeout in seconds
+    read: u32,
+}
+
+impl Default for CommonTimeouts {
+    fn default() -> Self {
+        Self {
+            connect: 5,
+            read: 30,
+        }
+    }
+}
+
+#[derive(Default, Serialize, TomlComment)]
+struct UpstreamConfig {
+    /// Upstream host
+    host: String,
+    #[serde(flatten)]
+    #[toml_comment(flatten)]
+    timeouts: CommonTimeouts,
+}
+
+#[derive(Default, Serialize, TomlComment)]
+struct WithFlatten {
+    upstream: UpstreamConfig,
+}
+
+#[test]
+fn flatten_into_parent() {
+    let expected = "\
+[upstream]
+# Upstream host
+host = \"\"
+# Connect timeout in seconds
+connect = 5
+# Read timeout in seconds
+read = 30
+";
+    let cfg = WithFlatten::default();
+    assert_eq!(cfg.to_commented_toml(), expected);
+    let paths: Vec<_> = cfg.fields().into_iter().map(|f| f.path).collect();
+    assert_eq!(
+        paths,
+        [
+            "upstream",
+            "upstream.host",
+            "upstream.connect",
+            "upstream.read"
+        ]
+    );
+}
+
+#[derive(Serialize, TomlComment)]
+struct DbPool {
+    /// Connections kept open
+    size: u32,
+}
+
+impl Default for DbPool {
+    fn default() -> Self {
+        Self { size: 10 }
+    }
+}
+
+/// Database connection
+#[derive(Serialize, TomlComment)]
+struct DbSettings {
+    /// Database host
+    host: String,
+    /// Database port
+    port: u16,
+    pool: DbPool,
+}
+
+impl Default for DbSettings {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 5432,
+            pool: DbPool::default(),
+        }
+    }
+}
+
+#[derive(Default, Serialize, TomlComment)]
+struct WithPrefixedFlatten {
+    /// Service name
+    name: String,
+    #[toml_comment(flatten, prefix = "db_")]
+    db: DbSettings,
+}
+
+#[test]
+fn flatten_with_prefix() {
+    let expected = "\
+# Service name
+name = \"\"
+# Database host
+db_host = \"localhost\"
+# Database port
+db_port = 5432
+
+[db_pool]
+# Connections kept open
+size = 10
+";
+    let cfg = WithPrefixedFlatten::default();
+    assert_eq!(cfg.to_commented_toml(), expected);
+    let paths: Vec<_> = cfg.fields().into_iter().map(|f| f.path).collect();
+    assert_eq!(
+        paths,
+        ["name", "db_host", "db_port", "db_pool", "db_pool.size"]
+    );
+}
+
+// --- Section renaming ---
+
+#[derive(Default, Serialize, TomlComment)]
+#[toml_comment(rename_sections = "kebab-case")]
+struct KebabSections {
+    /// Keys keep their own names
+    log_level: String,
+    #[serde(rename = "http-server")]
+    http_server: ServerConfig,
+}
+
+#[test]
+fn rename_sections_kebab() {
+    let expected = "\
+# Keys keep their own names
+log_level = \"\"
+[http-server]
+# Port to listen on
+port = 8080
+# Bind address
+host = \"127.0.0.1\"
+";
+    let cfg = KebabSections::default();
+    assert_eq!(cfg.to_commented_toml(), expected);
+    assert_eq!(cfg.fields()[2].path, "http-server.port");
+}
+
+// --- Single sections ---
+
+/// Connection pool
+#[derive(Serialize, TomlComment)]
+struct ShowPool {
+    /// Maximum connections
+    size: u32,
+}
+
+#[derive(Serialize, TomlComment)]
+struct ShowDatabase {
+    /// Connection URL
+    url: String,
+    /// Pool settings
+    pool: ShowPool,
+}
+
+#[derive(Serialize, TomlComment)]
+struct ShowConfig {
+    /// App name
+    name: String,
+    /// Storage
+    database: ShowDatabase,
+    /// Servers
+    upstream: Vec<Upstream>,
+}
+
+impl Default for ShowConfig {
+    fn default() -> Self {
+        Self {
+            name: "app".to_string(),
+            database: ShowDatabase {
+                url: "sqlite://data.db".to_string(),
+                pool: ShowPool { size: 8 },
+            },
+            upstream: vec![Upstream::default()],
+        }
+    }
+}
+
+#[test]
+fn render_single_section() {
+    let cfg = ShowConfig::default();
+    let expected = "\
+# Pool settings
+[database.pool]
+# Connection pool
+# Maximum connections
+size = 8
+";
+    assert_eq!(
+        cfg.render_section("database.pool").as_deref(),
+        Some(expected)
+    );
+
+    let database = cfg.render_section("database").unwrap();
+    assert!(database.starts_with("# Storage\n[database]\n# Connection URL\n"));
+    assert!(
+        database.ends_with("[database.pool]\n# Connection pool\n# Maximum connections\nsize = 8\n")
+    );
+    assert!(
+        cfg.render_section("upstream")
+            .unwrap()
+            .starts_with("# Servers\n[[upstream]]\n")
+    );
+
+    assert_eq!(cfg.render_section("name"), None);
+    assert_eq!(cfg.render_section("database.url"), None);
+    assert_eq!(cfg.render_section("missing"), None);
+}
+
+// --- Line stream ---
+
+#[derive(Serialize, TomlComment)]
+struct StreamConfig {
+    /// Allowed origins
+    origins: Vec<String>,
+    /// Storage
+    database: ShowDatabase,
+}
+
+#[test]
+fn lines_by_kind() {
+    let cfg = StreamConfig {
+        origins: (1..=4)
+            .map(|i| format!("https://mirror-{i}.example.com"))
+            .collect(),
+        database: ShowDatabase {
+            url: "sqlite://data.db".to_string(),
+            pool: ShowPool { size: 8 },
+        },
+    };
+    let lines: Vec<RenderedLine> = cfg.lines().collect();
+    assert_eq!(
+        lines[0],
+        RenderedLine::Comment("# Allowed origins".to_string())
+    );
+    let RenderedLine::KeyValue { key, value } = &lines[1] else {
+        panic!("expected an entry, got {:?}", lines[1]);
+    };
+    assert_eq!(key, "origins");
+    assert!(value.starts_with("[\n    \"https://mirror-1.example.com\",\n"));
+    assert!(value.ends_with("\"https://mirror-4.example.com\",\n]"));
+    assert_eq!(
+        lines[2..],
+        [
+            RenderedLine::Comment("# Storage".to_string()),
+            RenderedLine::SectionHeader {
+                path: "database".to_string(),
+                array: false,
+            },
+            RenderedLine::Comment("# Connection URL".to_string()),
+            RenderedLine::KeyValue {
+                key: "url".to_string(),
+                value: "\"sqlite://data.db\"".to_string(),
+            },
+            RenderedLine::Comment("# Pool settings".to_string()),
+            RenderedLine::SectionHeader {
+                path: "database.pool".to_string(),
+                array: false,
+            },
+            RenderedLine::Comment("# Connection pool".to_string()),
+            RenderedLine::Comment("# Maximum connections".to_string()),
+            RenderedLine::KeyValue {
+                key: "size".to_string(),
+                value: "8".to_string(),
+            },
+        ]
+    );
+
+    let joined: Vec<String> = lines.iter().map(ToString::to_string).collect();
+    assert_eq!(joined.join("\n") + "\n", cfg.to_commented_toml());
+
+    let tables: Vec<RenderedLine> = ShowConfig::default()
+        .lines()
+        .filter(|line| matches!(line, RenderedLine::SectionHeader { array: true, .. }))
+        .collect();
+    assert_eq!(
+        tables,
+        [RenderedLine::SectionHeader {
+            path: "upstream".to_string(),
+            array: true,
+        }]
+    );
+}
+
+#[derive(Serialize, Deserialize, TomlComment)]
+#[toml_comment(style(toml_pretty, indent = 2))]
+struct PrettyLines {
+    /// Notice
+    notice: PrettyText,
+}
+
+#[test]
+fn lines_multiline_string() {
+    let cfg = PrettyLines {
+        notice: PrettyText {
+            body: "line1\n[x]\n# y".to_string(),
+            footer: Vec::new(),
+        },
+    };
+    let lines: Vec<RenderedLine> = cfg.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            RenderedLine::Comment("# Notice".to_string()),
+            RenderedLine::SectionHeader {
+                path: "notice".to_string(),
+                array: false,
+            },
+            RenderedLine::Comment("# Message body".to_string()),
+            RenderedLine::KeyValue {
+                key: "body".to_string(),
+                value: "\"\"\"\nline1\n[x]\n# y\"\"\"".to_string(),
+            },
+            RenderedLine::Comment("# Lines of the footer".to_string()),
+            RenderedLine::KeyValue {
+                key: "footer".to_string(),
+                value: "[]".to_string(),
+            },
+        ]
+    );
+}
+
+// --- Trait objects ---
+
+#[test]
+fn render_through_trait_objects() {
+    use toml_comment::TomlCommentRender;
+
+    let plugins: Vec<Box<dyn TomlCommentRender>> = vec![
+        Box::new(MultiSection::default()),
+        Box::new(ShowConfig::default()),
+    ];
+    let rendered: Vec<String> = plugins.iter().map(|p| p.to_commented_toml()).collect();
+    assert_eq!(
+        rendered,
+        [
+            TomlComment::to_commented_toml(&MultiSection::default()),
+            TomlComment::to_commented_toml(&ShowConfig::default()),
+        ]
+    );
+}
+
+fn render_plugin(plugin: impl toml_comment::TomlCommentRender) -> String {
+    plugin.to_commented_toml()
+}
+
+#[test]
+fn forward_trait_objects() {
+    use toml_comment::TomlCommentRender;
+
+    let expected = TomlComment::to_commented_toml(&ShowConfig::default());
+    let boxed: Box<dyn TomlCommentRender> = Box::new(ShowConfig::default());
+    let borrowed: &dyn TomlCommentRender = &*boxed;
+    assert_eq!(render_plugin(borrowed), expected);
+    assert_eq!(render_plugin(boxed), expected);
+}
+
+// --- References and boxes ---
+
+fn render_any(config: impl TomlComment) -> String {
+    config.to_commented_toml()
+}
+
+#[test]
+fn references_and_boxes_render() {
+    let cfg = ShowConfig::default();
+    let expected = cfg.to_commented_toml();
+    assert_eq!(render_any(&cfg), expected);
+    assert_eq!(render_any(Box::new(ShowConfig::default())), expected);
+    assert_eq!(<Box<ShowConfig>>::default_toml(), expected);
+    assert_eq!(
+        <&ShowConfig>::render_section(&&cfg, "database.pool"),
+        cfg.render_section("database.pool")
+    );
+    assert_eq!(<&ShowConfig>::fields(&&cfg), cfg.fields());
+}
+
+// --- Map roots ---
+
+#[test]
+fn map_of_sections_at_root() {
+    let pools = BTreeMap::from([
+        ("replica".to_string(), ShowPool { size: 4 }),
+        ("primary".to_string(), ShowPool { size: 16 }),
+    ]);
+    let expected = "\
+[primary]
+# Connection pool
+# Maximum connections
+size = 16
+
+[replica]
+# Connection pool
+# Maximum connections
+size = 4
+";
+    assert_eq!(pools.to_commented_toml(), expected);
+    let parsed: BTreeMap<String, toml::Table> = toml::from_str(expected).unwrap();
+    assert_eq!(parsed["replica"]["size"].as_integer(), Some(4));
+
+    let hashed: HashMap<String, ShowPool> = pools
+        .iter()
+        .map(|(name, pool)| (name.clone(), ShowPool { size: pool.size }))
+        .collect();
+    assert_eq!(hashed.to_commented_toml(), expected);
+
+    assert_eq!(
+        pools.render_section("replica").as_deref(),
+        Some("[replica]\n# Connection pool\n# Maximum connections\nsize = 4\n")
+    );
+    let paths: Vec<String> = pools.fields().into_iter().map(|f| f.path).collect();
+    assert_eq!(
+        paths,
+        ["primary", "primary.size", "replica", "replica.size"]
+    );
+    assert_eq!(pools.fields()[0].ty, "ShowPool");
+}
+
+// --- Profiles ---
+
+/// Build settings
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug, Clone)]
+struct BuildProfile {
+    /// Optimization level
+    opt_level: u8,
+    /// Include debug info
+    debug: bool,
+    linker: LinkerSettings,
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug, Clone)]
+struct LinkerSettings {
+    /// Strip symbols from the binary
+    strip: bool,
+}
+
+#[test]
+fn profiles_override_default() {
+    let default = BuildProfile {
+        opt_level: 0,
+        debug: true,
+        linker: LinkerSettings { strip: false },
+    };
+    let release = BuildProfile {
+        opt_level: 3,
+        debug: false,
+        linker: LinkerSettings { strip: true },
+    };
+    let dev = BuildProfile {
+        opt_level: 1,
+        ..default.clone()
+    };
+    let profiles = toml_comment::Profiles::new(default.clone())
+        .profile("dev", dev.clone())
+        .profile("release", release.clone());
+    let out = profiles.to_commented_toml();
+    let expected = "\
+# Build settings
+
+[default]
+# Optimization level
+opt_level = 0
+# Include debug info
+debug = true
+
+[default.linker]
+# Strip symbols from the binary
+strip = false
+
+[profile.dev]
+# Optimization level
+opt_level = 1
+
+[profile.release]
+# Optimization level
+opt_level = 3
+# Include debug info
+debug = false
+
+[profile.release.linker]
+# Strip symbols from the binary
+strip = true
+";
+    assert_eq!(out, expected);
+
+    let load = toml_comment::Profiles::<BuildProfile>::load;
+    assert_eq!(load(&out, "dev").unwrap(), dev);
+    assert_eq!(load(&out, "release").unwrap(), release);
+    let err = load(&out, "bench").unwrap_err();
+    assert!(err.to_string().contains("no [profile.bench] table"));
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug, Clone)]
+struct TailProfile {
+    linker: LinkerSettings,
+    /// Parallel jobs
+    jobs: u32,
+}
+
+#[test]
+fn profiles_root_key_after_section() {
+    let default = TailProfile {
+        linker: LinkerSettings { strip: false },
+        jobs: 1,
+    };
+    let dev = TailProfile {
+        linker: LinkerSettings { strip: true },
+        jobs: 8,
+    };
+    let out = toml_comment::Profiles::new(default)
+        .profile("dev", dev.clone())
+        .to_commented_toml();
+    assert!(out.ends_with(
+        "\
+[profile.dev]
+# Parallel jobs
+jobs = 8
+
+[profile.dev.linker]
+# Strip symbols from the binary
+strip = true
+"
+    ));
+    assert_eq!(
+        toml_comment::Profiles::<TailProfile>::load(&out, "dev").unwrap(),
+        dev
+    );
+}
+
+// --- Split files ---
+
+/// Service settings
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct SplitConfig {
+    /// Service name
+    name: String,
+    /// HTTP server
+    server: SplitServer,
+    database: SplitDatabase,
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct SplitServer {
+    /// Port to listen on
+    port: u16,
+}
+
+/// Database connection
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct SplitDatabase {
+    /// Connection URL
+    url: String,
+}
+
+#[test]
+fn split_into_files() {
+    let cfg = SplitConfig {
+        name: "api".to_string(),
+        server: SplitServer { port: 8080 },
+        database: SplitDatabase {
+            url: "sqlite://data.db".to_string(),
+        },
+    };
+    let files = cfg.to_commented_files();
+    let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["config.toml", "server.toml", "database.toml"]);
+    assert_eq!(
+        files[0].1,
+        "# Service settings\n# Service name\nname = \"api\"\n"
+    );
+    assert_eq!(
+        files[1].1,
+        "# HTTP server\n[server]\n# Port to listen on\nport = 8080\n"
+    );
+    assert_eq!(
+        files[2].1,
+        "[database]\n# Database connection\n# Connection URL\nurl = \"sqlite://data.db\"\n"
+    );
+
+    let loaded: SplitConfig = toml_comment::load_files(files.iter().map(|(_, text)| text)).unwrap();
+    assert_eq!(loaded, cfg);
+    let overridden: SplitConfig = toml_comment::load_files(
+        files
+            .iter()
+            .map(|(_, text)| text.as_str())
+            .chain(["[server]\nport = 9090\n"]),
+    )
+    .unwrap();
+    assert_eq!(overridden.server.port, 9090);
+    assert_eq!(overridden.database, cfg.database);
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct SplitTail {
+    server: SplitServer,
+    /// Worker threads
+    workers: u32,
+    /// Fallback database
+    replica: Option<SplitDatabase>,
+}
+
+#[test]
+fn split_keeps_top_level_keys_after_sections() {
+    let cfg = SplitTail {
+        server: SplitServer { port: 8080 },
+        workers: 4,
+        replica: Some(SplitDatabase {
+            url: "sqlite://replica.db".to_string(),
+        }),
+    };
+    let files = cfg.to_commented_files();
+    assert_eq!(files[0].0, "config.toml");
+    assert_eq!(
+        files[0].1,
+        "\
+# Worker threads
+workers = 4
+# Fallback database
+replica = { url = \"sqlite://replica.db\" }
+"
+    );
+    let loaded: SplitTail = toml_comment::load_files(files.iter().map(|(_, text)| text)).unwrap();
+    assert_eq!(loaded, cfg);
+}
+
+// --- Load options ---
+
+#[derive(Serialize, Deserialize, TomlComment, Default, PartialEq, Debug)]
+struct LenientConfig {
+    /// Maximum open connections
+    max_connections: u32,
+    server: LenientServer,
+    /// Labels for metrics
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, TomlComment, Default, PartialEq, Debug)]
+struct LenientServer {
+    /// Bind address
+    #[serde(alias = "bind")]
+    bind_addr: String,
+}
+
+#[test]
+fn load_folding_keys() {
+    let opts = toml_comment::LoadOptions {
+        fold_keys: true,
+        ..Default::default()
+    };
+    let text = "\
+Max-Connections = 64
+
+[Server]
+BIND_ADDR = \"0.0.0.0\"
+
+[labels]
+Team = \"core\"
+";
+    let loaded: LenientConfig = toml_comment::load_files_with([text], &opts).unwrap();
+    assert_eq!(loaded.max_connections, 64);
+    assert_eq!(loaded.server.bind_addr, "0.0.0.0");
+    assert_eq!(loaded.labels["Team"], "core");
+
+    let profiles = "\
+[default]
+max_connections = 8
+server.bind_addr = \"127.0.0.1\"
+
+[profile.prod]
+MAX_CONNECTIONS = 512
+";
+    let prod = toml_comment::Profiles::<LenientConfig>::load_with(profiles, "prod", &opts).unwrap();
+    assert_eq!(prod.max_connections, 512);
+
+    let strict = toml_comment::load_files::<LenientConfig, _>([text]);
+    assert!(strict.is_err());
+}
+
+#[test]
+fn load_folding_renamed_keys() {
+    let opts = toml_comment::LoadOptions {
+        fold_keys: true,
+        ..Default::default()
+    };
+    let text = "\
+Addr = \"10.0.0.1\"
+max-connections = 4
+
+[TLS-Options]
+verify_peer = true
+";
+    let loaded: WithRenamedKeys = toml_comment::load_files_with([text], &opts).unwrap();
+    assert_eq!(loaded.listen_address, "10.0.0.1");
+    assert_eq!(loaded.max_connections, 4);
+    assert!(loaded.tls_options.verify_peer);
+}
+
+#[test]
+fn fold_keys_in_place() {
+    let text = "\
+# Pool size
+Max-Connections = 64 # per worker
+
+[Server]
+# Where to listen
+BIND = \"0.0.0.0\"
+
+[labels]
+Team = \"core\"
+note = \"\"\"
+Max-Connections = 1
+\"\"\"
+";
+    let folded = toml_comment::fold_keys::<LenientConfig>(text).unwrap();
+    assert_eq!(
+        folded,
+        "\
+# Pool size
+max_connections = 64 # per worker
+
+[server]
+# Where to listen
+bind_addr = \"0.0.0.0\"
+
+[labels]
+Team = \"core\"
+note = \"\"\"
+Max-Connections = 1
+\"\"\"
+"
+    );
+    let loaded: LenientConfig = toml::from_str(&folded).unwrap();
+    assert_eq!(loaded.max_connections, 64);
+
+    let inline = "max_connections = 1\nMax_Connections = 2\nServer = { \"Bind-Addr\" = \"a\" }\n";
+    assert_eq!(
+        toml_comment::fold_keys::<LenientConfig>(inline).unwrap(),
+        "max_connections = 1\nMax_Connections = 2\nserver = { bind_addr = \"a\" }\n"
+    );
+    assert!(toml_comment::fold_keys::<LenientConfig>("a = ").is_err());
+}
+
+#[test]
+fn load_denying_unknown_keys() {
+    let text = "\
+max_connections = 8
+max_conns = 9
+
+[server]
+bind = \"0.0.0.0\"
+port = 80
+
+[labels]
+team = \"core\"
+";
+    let loaded: LenientConfig = toml_comment::load_files([text]).unwrap();
+    assert_eq!(loaded.server.bind_addr, "0.0.0.0");
+
+    let opts = toml_comment::LoadOptions {
+        unknown_keys: toml_comment::UnknownKeys::Deny,
+        ..Default::default()
+    };
+    let err = toml_comment::load_files_with::<LenientConfig, _>([text], &opts).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("unknown keys: max_conns, server.port"),
+        "{err}"
+    );
+    assert_eq!(
+        toml_comment::unknown_keys::<LenientConfig>(text).unwrap(),
+        ["max_conns", "server.port"]
+    );
+    let fixed = text
+        .replace("max_conns = 9\n", "")
+        .replace("port = 80\n", "");
+    let loaded: LenientConfig = toml_comment::load_files_with([fixed], &opts).unwrap();
+    assert_eq!(loaded.labels["team"], "core");
+}
+
+static WARNED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+#[test]
+fn load_warning_about_unknown_keys() {
+    let text = "\
+max_connections = 8
+max_conns = 9
+
+[server]
+bind = \"0.0.0.0\"
+";
+    let opts = toml_comment::LoadOptions {
+        unknown_keys: toml_comment::UnknownKeys::Warn(|key| {
+            WARNED.lock().unwrap().push(key.to_string());
+        }),
+        ..Default::default()
+    };
+    let loaded: LenientConfig = toml_comment::load_files_with([text], &opts).unwrap();
+    assert_eq!(loaded.server.bind_addr, "0.0.0.0");
+    assert_eq!(*WARNED.lock().unwrap(), ["max_conns"]);
+
+    let opts = toml_comment::LoadOptions {
+        unknown_keys: toml_comment::UnknownKeys::Deny,
+        aliases: toml_comment::Aliases::Reject,
+        ..Default::default()
+    };
+    let err = toml_comment::load_files_with::<LenientConfig, _>([text], &opts).unwrap_err();
+    assert!(
+        err.to_string().contains("unknown keys: server.bind"),
+        "{err}"
+    );
+    let opts = toml_comment::LoadOptions {
+        aliases: toml_comment::Aliases::Reject,
+        ..Default::default()
+    };
+    let current = text.replace("bind =", "bind_addr =");
+    assert!(toml_comment::load_files_with::<LenientConfig, _>([text], &opts).is_err());
+    assert!(toml_comment::load_files_with::<LenientConfig, _>([current], &opts).is_ok());
+}
+
+// --- Arrays of tables ---
+
+/// One backend server
+#[derive(Serialize, TomlComment)]
+struct Upstream {
+    /// Backend address
+    addr: String,
+    /// Relative weight
+    weight: u32,
+}
+
+impl Default for Upstream {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:80".to_string(),
+            weight: 1,
+        }
+    }
+}
+
+#[derive(Serialize, TomlComment)]
+struct Balancer {
+    /// Balancing strategy
+    strategy: String,
+    /// Servers to balance across
+    upstream: Vec<Upstream>,
+    /// Standby servers
+    #[toml_comment(section_docs = "above")]
+    standby: Vec<Upstream>,
+    /// No entries, nothing rendered
+    spare: Vec<Upstream>,
+}
+
+impl Default for Balancer {
+    fn default() -> Self {
+        Self {
+            strategy: "round-robin".to_string(),
+            upstream: vec![
+                Upstream::default(),
+                Upstream {
+                    addr: "127.0.0.1:81".to_string(),
+                    weight: 2,
+                },
+            ],
+            standby: vec![Upstream::default()],
+            spare: Vec::new(),
+        }
+    }
+}
+
+#[test]
+fn array_of_tables() {
+    let expected = "\
+# Balancing strategy
+strategy = \"round-robin\"
+# Servers to balance across
+[[upstream]]
+# One backend server
+# Backend address
+addr = \"127.0.0.1:80\"
+# Relative weight
+weight = 1
+
+[[upstream]]
+# One backend server
+# Backend address
+addr = \"127.0.0.1:81\"
+# Relative weight
+weight = 2
+
+# Standby servers
+# One backend server
+[[standby]]
+# Backend address
+addr = \"127.0.0.1:80\"
+# Relative weight
+weight = 1
+";
+    let cfg = Balancer::default();
+    assert_eq!(cfg.to_commented_toml(), expected);
+    let parsed: toml::Table = toml::from_str(expected).unwrap();
+    assert_eq!(parsed["upstream"].as_array().unwrap().len(), 2);
+
+    let fields = cfg.fields();
+    assert_eq!(fields[1].kind, toml_comment::FieldKind::Tables);
+    assert_eq!(fields[4].path, "upstream[1].addr");
+}
+
+// --- Per-key map docs ---
+
+fn flag_docs(key: &str) -> Option<String> {
+    match key {
+        "beta_ui" => Some("Opt into the redesigned UI".to_string()),
+        "fast_path" => Some("Skip validation\n(unsafe with untrusted input)".to_string()),
+        _ => None,
+    }
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithKeyDocs {
+    /// Feature flags
+    #[toml_comment(key_docs = flag_docs)]
+    flags: BTreeMap<String, bool>,
+}
+
+impl Default for WithKeyDocs {
+    fn default() -> Self {
+        let flags = [("beta_ui", false), ("fast_path", true), ("legacy", false)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        Self { flags }
+    }
+}
+
+#[test]
+fn map_key_docs() {
+    let expected = "\
+# Feature flags
+# Opt into the redesigned UI
+beta_ui = false
+# Skip validation
+# (unsafe with untrusted input)
+fast_path = true
+legacy = false
+";
+    assert_eq!(WithKeyDocs::default_toml(), expected);
+}
+
+// --- Dynamic comments ---
+
+#[derive(Serialize, TomlComment)]
+struct WithDocFn {
+    /// Worker threads
+    #[toml_comment(doc_fn = worker_docs)]
+    workers: usize,
+    #[toml_comment(doc_fn = crate::data_dir_docs)]
+    data_dir: String,
+}
+
+impl WithDocFn {
+    fn worker_docs(&self) -> Vec<String> {
+        vec![format!("Detected {} CPUs", 8)]
+    }
+}
+
+fn data_dir_docs(cfg: &WithDocFn) -> Vec<String> {
+    vec![format!("Found existing data in {}", cfg.data_dir)]
+}
+
+impl Default for WithDocFn {
+    fn default() -> Self {
+        Self {
+            workers: 8,
+            data_dir: "/srv/data".to_string(),
+        }
+    }
+}
+
+#[test]
+fn doc_fn_comments() {
+    let expected = "\
+# Worker threads
+# Detected 8 CPUs
+workers = 8
+# Found existing data in /srv/data
+data_dir = \"/srv/data\"
+";
+    assert_eq!(WithDocFn::default_toml(), expected);
+}
+
+// --- Non-string map keys ---
+
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+enum Tier {
+    Free,
+    Pro,
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithOddKeys {
+    /// Routes by port
+    routes: BTreeMap<u16, String>,
+    /// Limits by tier
+    limits: BTreeMap<Tier, u32>,
+    /// Headers by name
+    headers: HashMap<String, String>,
+}
+
+impl Default for WithOddKeys {
+    fn default() -> Self {
+        Self {
+            routes: [(8080, "/api"), (443, "/")]
+                .into_iter()
+                .map(|(k, v)| (k, v.to_string()))
+                .collect(),
+            limits: [(Tier::Free, 10), (Tier::Pro, 1000)].into_iter().collect(),
+            headers: [("X-Request Id", "on"), ("Accept", "*/*")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+}
+
+#[test]
+fn non_string_map_keys() {
+    let expected = "\
+# Routes by port
+443 = \"/\"
+8080 = \"/api\"
+# Limits by tier
+free = 10
+pro = 1000
+# Headers by name
+Accept = \"*/*\"
+\"X-Request Id\" = \"on\"
+";
+    let toml = WithOddKeys::default_toml();
+    assert_eq!(toml, expected);
+    assert!(toml.parse::<toml::Table>().is_ok());
+}
+
+#[derive(Serialize, TomlComment, Default)]
+struct WithTupleKeys {
+    /// Cells by position
+    grid: BTreeMap<(u8, u8), String>,
+}
+
+#[test]
+fn unsupported_map_key_error() {
+    let cfg = WithTupleKeys {
+        grid: BTreeMap::from([((1, 2), "x".to_string())]),
+    };
+    let err = cfg
+        .try_to_commented_toml_with(&Default::default())
+        .unwrap_err();
+    assert_eq!(
+        err,
+        toml_comment::RenderError::MapKey {
+            key: "[1, 2]".to_string()
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "TomlComment: map key [1, 2] is not a string, integer, boolean or unit enum variant"
+    );
+}
+
+// --- Map subsections ---
+
+fn host_docs(key: &str) -> Option<String> {
+    (key == "db.internal").then(|| "Primary database".to_string())
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithHostTables {
+    /// Log level
+    level: String,
+    /// Per-host overrides
+    #[toml_comment(map_tables, key_docs = host_docs)]
+    hosts: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Default for WithHostTables {
+    fn default() -> Self {
+        let host = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        Self {
+            level: "info".to_string(),
+            hosts: [
+                ("cache".to_string(), host(&[("port", "6379")])),
+                (
+                    "db.internal".to_string(),
+                    host(&[("port", "5432"), ("user", "app")]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+}
+
+#[test]
+fn map_tables_subsections() {
+    let expected = "\
+# Log level
+level = \"info\"
+# Per-host overrides
+[hosts.cache]
+port = \"6379\"
+
+# Primary database
+[hosts.\"db.internal\"]
+port = \"5432\"
+user = \"app\"
+";
+    let toml = WithHostTables::default_toml();
+    assert_eq!(toml, expected);
+    let parsed: toml::Table = toml.parse().unwrap();
+    assert_eq!(parsed["hosts"]["db.internal"]["user"].as_str(), Some("app"));
+}
+
+#[test]
+fn map_tables_empty_subsection() {
+    let cfg = WithHostTables {
+        level: "info".to_string(),
+        hosts: BTreeMap::from([
+            ("a".to_string(), BTreeMap::new()),
+            (
+                "b".to_string(),
+                BTreeMap::from([("k".to_string(), "v".to_string())]),
+            ),
+        ]),
+    };
+    let toml = cfg.to_commented_toml();
+    assert!(toml.ends_with("# Per-host overrides\n[hosts.a]\n\n[hosts.b]\nk = \"v\"\n"));
+    let parsed: toml::Table = toml.parse().unwrap();
+    assert!(parsed["hosts"]["a"].as_table().unwrap().is_empty());
+
+    let mut extra = toml::Table::new();
+    extra.insert("deep".to_string(), toml::Value::Table(toml::Table::new()));
+    let dynamic = WithDynamicTable {
+        t: toml::Value::Table(extra),
+    };
+    let parsed: toml::Table = dynamic.to_commented_toml().parse().unwrap();
+    assert!(parsed["t"]["deep"].as_table().unwrap().is_empty());
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithDynamicTable {
+    t: toml::Value,
+}
+
+impl Default for WithDynamicTable {
+    fn default() -> Self {
+        Self {
+            t: toml::Value::Table(toml::Table::new()),
+        }
+    }
+}
+
+// --- Dynamic values ---
+
+#[derive(Serialize, TomlComment)]
+struct WithExtensions {
+    /// Plugin name
+    name: String,
+    /// Plugin log level
+    level: serde_json::Value,
+    /// Passed to the plugin as-is
+    extra: serde_json::Value,
+    /// Not set
+    unset: serde_json::Value,
+}
+
+impl Default for WithExtensions {
+    fn default() -> Self {
+        Self {
+            name: "lint".to_string(),
+            level: serde_json::json!("warn"),
+            extra: serde_json::json!({
+                "strict": true,
+                "rules": { "max_len": 100 }
+            }),
+            unset: serde_json::Value::Null,
+        }
+    }
+}
+
+#[test]
+fn serde_json_values() {
+    let expected = "\
+# Plugin name
+name = \"lint\"
+# Plugin log level
+level = \"warn\"
+
+# Passed to the plugin as-is
+[extra]
+strict = true
+
+[extra.rules]
+max_len = 100
+";
+    let toml = WithExtensions::default_toml();
+    assert_eq!(toml, expected);
+    let parsed: toml::Table = toml.parse().unwrap();
+    assert_eq!(parsed["extra"]["rules"]["max_len"].as_integer(), Some(100));
+
+    let fields = WithExtensions::default().fields();
+    assert_eq!(fields[1].kind, toml_comment::FieldKind::Value);
+    assert_eq!(fields[2].kind, toml_comment::FieldKind::Map);
+    assert_eq!(fields[3].value, None);
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithPluginConfig {
+    /// Plugin timeout
+    timeout: toml::Value,
+    /// Stored for the plugin
+    plugin: toml::Table,
+    /// Left empty
+    other: toml::Table,
+}
+
+impl Default for WithPluginConfig {
+    fn default() -> Self {
+        Self {
+            timeout: toml::Value::Integer(30),
+            plugin: toml::toml! {
+                name = "fmt"
+                args = ["--check"]
+                [env]
+                RUST_LOG = "debug"
+            },
+            other: toml::Table::new(),
+        }
+    }
+}
+
+#[test]
+fn toml_values() {
+    let expected = "\
+# Plugin timeout
+timeout = 30
+
+# Stored for the plugin
+[plugin]
+args = [\"--check\"]
+name = \"fmt\"
+
+[plugin.env]
+RUST_LOG = \"debug\"
+
+# Left empty
+[other]
+";
+    let toml = WithPluginConfig::default_toml();
+    assert_eq!(toml, expected);
+    let parsed: toml::Table = toml.parse().unwrap();
+    assert_eq!(parsed["plugin"]["env"]["RUST_LOG"].as_str(), Some("debug"));
+}
+
+// --- Optional collections ---
+
+#[derive(Serialize, Deserialize, TomlComment, Default)]
+struct Mirror {
+    /// Mirror URL
+    url: String,
+}
+
+#[derive(Serialize, Deserialize, TomlComment, Default)]
+struct WithOptionalCollections {
+    /// Allowed origins
+    origins: Option<Vec<String>>,
+    /// Trusted proxies
+    proxies: Option<Vec<String>>,
+    /// Extra labels
+    labels: Option<BTreeMap<String, String>>,
+    /// Download mirrors
+    mirrors: Option<Vec<Mirror>>,
+}
+
+#[test]
+fn optional_collections_none() {
+    let expected = "\
+# Allowed origins
+# origins = []
+# Trusted proxies
+# proxies = []
+# Extra labels
+# [labels]
+
+# Download mirrors
+# [[mirrors]]
+";
+    assert_eq!(WithOptionalCollections::default_toml(), expected);
+}
+
+#[test]
+fn optional_collections_some() {
+    let cfg = WithOptionalCollections {
+        origins: Some(vec![
+            "https://app.example.com".to_string(),
+            "https://admin.example.com".to_string(),
+            "http://localhost:3000".to_string(),
+        ]),
+        proxies: Some(Vec::new()),
+        labels: Some(BTreeMap::new()),
+        mirrors: Some(vec![Mirror {
+            url: "https://eu.example.com".to_string(),
+        }]),
+    };
+    let expected = "\
+# Allowed origins
+origins = [
+    \"https://app.example.com\",
+    \"https://admin.example.com\",
+    \"http://localhost:3000\",
+]
+# Trusted proxies
+proxies = []
+# Extra labels
+[labels]
+
+# Download mirrors
+[[mirrors]]
+# Mirror URL
+url = \"https://eu.example.com\"
+";
+    let toml = cfg.to_commented_toml();
+    assert_eq!(toml, expected);
+    let parsed: WithOptionalCollections = toml::from_str(&toml).unwrap();
+    assert_eq!(parsed.origins.unwrap().len(), 3);
+    assert_eq!(parsed.proxies, Some(Vec::new()));
+    assert_eq!(parsed.labels, Some(BTreeMap::new()));
+    assert_eq!(parsed.mirrors.unwrap()[0].url, "https://eu.example.com");
+}
+
+#[derive(Serialize, Deserialize, TomlComment)]
+struct MirrorsAfterSection {
+    /// HTTP server
+    server: SplitServer,
+    /// Download mirrors
+    mirrors: Option<Vec<Mirror>>,
+}
+
+#[test]
+fn optional_tables_empty_after_section() {
+    let cfg = MirrorsAfterSection {
+        server: SplitServer { port: 80 },
+        mirrors: Some(Vec::new()),
+    };
+    let expected = "\
+# Download mirrors
+mirrors = []
+# HTTP server
+[server]
+# Port to listen on
+port = 80
+";
+    let toml = cfg.to_commented_toml();
+    assert_eq!(toml, expected);
+    let parsed: MirrorsAfterSection = toml::from_str(&toml).unwrap();
+    assert!(parsed.mirrors.is_some_and(|mirrors| mirrors.is_empty()));
+    assert_eq!(parsed.server, cfg.server);
+}
+
+// --- Recursive sections ---
+
+#[derive(Serialize, Deserialize, TomlComment, Default)]
+struct Menu {
+    /// Entries in this menu
+    items: Vec<MenuItem>,
+}
+
+#[derive(Serialize, Deserialize, TomlComment, Default)]
+struct MenuItem {
+    /// Label shown to the user
+    label: String,
+    /// Nested menu opened by this item
+    submenu: Option<Box<Menu>>,
+}
+
+fn sample_menu() -> Menu {
+    let item = |label: &str, submenu: Option<Menu>| MenuItem {
+        label: label.to_string(),
+        submenu: submenu.map(Box::new),
+    };
+    Menu {
+        items: vec![
+            item(
+                "File",
+                Some(Menu {
+                    items: vec![item("Open", None)],
+                }),
+            ),
+            item("Help", None),
+        ],
+    }
+}
+
+#[test]
+fn recursive_boxed_sections() {
+    let expected = "\
+# Entries in this menu
+[[items]]
+# Label shown to the user
+label = \"File\"
+# Nested menu opened by this item
+[items.submenu]
+# Entries in this menu
+[[items.submenu.items]]
+# Label shown to the user
+label = \"Open\"
+
+[[items]]
+# Label shown to the user
+label = \"Help\"
+";
+    let toml = sample_menu().to_commented_toml();
+    assert_eq!(toml, expected);
+    let parsed: Menu = toml::from_str(&toml).unwrap();
+    let submenu = parsed.items[0].submenu.as_ref().unwrap();
+    assert_eq!(submenu.items[0].label, "Open");
+    assert!(parsed.items[1].submenu.is_none());
+}
+
+#[test]
+#[should_panic(expected = "past max_depth of 2")]
+fn recursive_sections_depth_limit() {
+    sample_menu().to_commented_toml_with(&toml_comment::RenderOptions {
+        max_depth: Some(2),
+        ..Default::default()
+    });
+}
+
+#[test]
+fn recursive_sections_depth_error() {
+    let opts = toml_comment::RenderOptions {
+        max_depth: Some(2),
+        ..Default::default()
+    };
+    let err = sample_menu().try_to_commented_toml_with(&opts).unwrap_err();
+    assert_eq!(
+        err,
+        toml_comment::RenderError::TooDeep {
+            table: "items.submenu.items".to_string(),
+            depth: 3,
+            max_depth: 2,
+        }
+    );
+    let opts = toml_comment::RenderOptions {
+        max_depth: Some(3),
+        ..Default::default()
+    };
+    assert_eq!(
+        sample_menu().try_to_commented_toml_with(&opts).unwrap(),
+        sample_menu().to_commented_toml()
+    );
+}
+
+// --- Enum sections ---
+
+/// Where uploads are stored
+#[derive(Serialize, Deserialize, TomlComment)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum StorageBackend {
+    /// Amazon S3 or a compatible service
+    S3 {
+        /// Bucket name
+        bucket: String,
+        /// Region, defaults to the SDK's
+        region: Option<String>,
+    },
+    /// Local disk
+    Local {
+        /// Directory to write to
+        path: String,
+    },
+}
+
+impl Default for StorageBackend {
+    fn default() -> Self {
+        Self::Local {
+            path: "/var/lib/uploads".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, TomlComment, Default)]
+struct WithStorage {
+    /// Max upload size in MB
+    max_size: u32,
+    /// Storage backend
+    storage: StorageBackend,
+}
+
+#[test]
+fn enum_struct_variant_section() {
+    let expected = "\
+# Max upload size in MB
+max_size = 0
+# Storage backend
+[storage]
+# Where uploads are stored
+# Local disk
+type = \"local\"
+# Directory to write to
+path = \"/var/lib/uploads\"
+";
+    assert_eq!(WithStorage::default_toml(), expected);
+
+    let cfg = WithStorage {
+        max_size: 10,
+        storage: StorageBackend::S3 {
+            bucket: "uploads".to_string(),
+            region: None,
+        },
+    };
+    let toml = cfg.to_commented_toml();
+    assert!(toml.contains(
+        "# Amazon S3 or a compatible service\ntype = \"s3\"\n# Bucket name\nbucket = \"uploads\"\n"
+    ));
+    let parsed: WithStorage = toml::from_str(&toml).unwrap();
+    assert!(matches!(parsed.storage, StorageBackend::S3 { bucket, .. } if bucket == "uploads"));
+
+    let fields = cfg.fields();
+    assert_eq!(fields[2].path, "storage.type");
+    assert_eq!(fields[3].path, "storage.bucket");
+}
+
+/// Cache backend
+#[derive(Serialize, Deserialize, TomlComment)]
+#[serde(tag = "kind", content = "options", rename_all = "kebab-case")]
+enum CacheBackend {
+    /// In-process cache
+    InMemory {
+        /// Max entries kept
+        capacity: u32,
+    },
+    /// No caching
+    Disabled,
+}
+
+impl Default for CacheBackend {
+    fn default() -> Self {
+        Self::InMemory { capacity: 1024 }
+    }
+}
+
+#[derive(Serialize, Deserialize, TomlComment, Default)]
+struct WithCache {
+    /// Cache settings
+    cache: CacheBackend,
+}
+
+#[test]
+fn enum_adjacently_tagged() {
+    let expected = "\
+# Cache settings
+[cache]
+# Cache backend
+# In-process cache
+kind = \"in-memory\"
+
+[cache.options]
+# Max entries kept
+capacity = 1024
+";
+    let toml = WithCache::default_toml();
+    assert_eq!(toml, expected);
+    let parsed: WithCache = toml::from_str(&toml).unwrap();
+    assert!(matches!(
+        parsed.cache,
+        CacheBackend::InMemory { capacity: 1024 }
+    ));
+    assert_eq!(
+        WithCache::default().fields()[2].path,
+        "cache.options.capacity"
+    );
+
+    let disabled = WithCache {
+        cache: CacheBackend::Disabled,
+    };
+    let toml = disabled.to_commented_toml();
+    assert!(toml.ends_with("# No caching\nkind = \"disabled\"\n"));
+    let parsed: WithCache = toml::from_str(&toml).unwrap();
+    assert!(matches!(parsed.cache, CacheBackend::Disabled));
+}
+
+/// Where logs go
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+enum LogSink {
+    /// Append to a file
+    File {
+        /// Path to the log file
+        path: String,
+    },
+    /// Forward to syslog
+    Syslog(SyslogOptions),
+    /// Standard error
+    Stderr,
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct SyslogOptions {
+    /// Syslog facility
+    facility: String,
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct WithLogSink {
+    /// Log level
+    level: String,
+    /// Log destination
+    sink: LogSink,
+}
+
+#[test]
+fn enum_externally_tagged() {
+    let cfg = WithLogSink {
+        level: "info".to_string(),
+        sink: LogSink::File {
+            path: "/var/log/app.log".to_string(),
+        },
+    };
+    let expected = "\
+# Log level
+level = \"info\"
+# Log destination
+[sink]
+# Where logs go
+
+# Append to a file
+[sink.File]
+# Path to the log file
+path = \"/var/log/app.log\"
+";
+    let toml = cfg.to_commented_toml();
+    assert_eq!(toml, expected);
+    assert_eq!(toml::from_str::<WithLogSink>(&toml).unwrap(), cfg);
+    let paths: Vec<String> = cfg.fields().into_iter().map(|f| f.path).collect();
+    assert_eq!(paths, ["level", "sink", "sink.File", "sink.File.path"]);
+
+    for sink in [
+        LogSink::Syslog(SyslogOptions {
+            facility: "local0".to_string(),
+        }),
+        LogSink::Stderr,
+    ] {
+        let cfg = WithLogSink {
+            level: "warn".to_string(),
+            sink,
+        };
+        let toml = cfg.to_commented_toml();
+        assert_eq!(toml::from_str::<WithLogSink>(&toml).unwrap(), cfg, "{toml}");
+    }
+    let stderr = WithLogSink {
+        level: "warn".to_string(),
+        sink: LogSink::Stderr,
+    };
+    assert_eq!(
+        stderr.to_commented_toml(),
+        "# Log level\nlevel = \"warn\"\n# Log destination\n# Standard error\nsink = \"Stderr\"\n"
+    );
+    let sink = &stderr.fields()[1];
+    assert_eq!(sink.kind, toml_comment::FieldKind::Value);
+    assert_eq!(sink.value, Some(toml::Value::String("Stderr".to_string())));
+    assert_eq!(stderr.render_section("sink"), None);
+}
+
+/// How clients authenticate
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+#[serde(untagged)]
+enum AuthMethod {
+    /// A static token
+    Token {
+        /// Bearer token
+        token: String,
+    },
+    /// Username and password
+    Basic {
+        /// Login name
+        user: String,
+        /// Password
+        password: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct WithAuth {
+    /// Authentication
+    auth: AuthMethod,
+}
+
+#[test]
+fn enum_untagged_section() {
+    let cfg = WithAuth {
+        auth: AuthMethod::Basic {
+            user: "admin".to_string(),
+            password: "hunter2".to_string(),
+        },
+    };
+    let expected = "\
+# Authentication
+[auth]
+# How clients authenticate
+# Username and password
+# Login name
+user = \"admin\"
+# Password
+password = \"hunter2\"
+";
+    let toml = cfg.to_commented_toml();
+    assert_eq!(toml, expected);
+    assert_eq!(toml::from_str::<WithAuth>(&toml).unwrap(), cfg);
+
+    let cfg = WithAuth {
+        auth: AuthMethod::Token {
+            token: "abc".to_string(),
+        },
+    };
+    let toml = cfg.to_commented_toml();
+    assert_eq!(toml::from_str::<WithAuth>(&toml).unwrap(), cfg);
+    let paths: Vec<String> = cfg.fields().into_iter().map(|f| f.path).collect();
+    assert_eq!(paths, ["auth", "auth.token"]);
+}
+
+#[test]
+fn enum_variant_samples() {
+    let expected = "\
+# [storage]
+# # Where uploads are stored
+# # Amazon S3 or a compatible service
+# type = \"s3\"
+# # Bucket name
+# bucket = \"\"
+
+[storage]
+# Where uploads are stored
+# Local disk
+type = \"local\"
+# Directory to write to
+path = \"/var/lib/uploads\"
+";
+    assert_eq!(StorageBackend::variant_samples("storage"), expected);
+    let parsed: WithStorage = toml::from_str(&format!("max_size = 1\n{expected}")).unwrap();
+    assert!(matches!(parsed.storage, StorageBackend::Local { .. }));
+
+    let cache = CacheBackend::variant_samples("cache");
+    assert!(cache.starts_with("[cache]\n# Cache backend\n# In-process cache\n"));
+    assert!(cache.contains("[cache.options]\n# Max entries kept\ncapacity = 1024\n"));
+    assert!(
+        cache.ends_with("\n# [cache]\n# # Cache backend\n# # No caching\n# kind = \"disabled\"\n")
+    );
+
+    assert_eq!(WithStorage::variant_samples("storage"), "");
+}
+
+// --- Untagged enums ---
+
+#[derive(Serialize, Deserialize, toml_comment::TypeDocs)]
+#[serde(untagged)]
+enum Hosts {
+    /// a single host
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Serialize, Deserialize, TomlComment)]
+struct WithUntagged {
+    /// Hosts to connect to
+    #[toml_comment(untagged)]
+    hosts: Hosts,
+    #[toml_comment(untagged)]
+    fallback: Option<Hosts>,
+}
+
+impl Default for WithUntagged {
+    fn default() -> Self {
+        Self {
+            hosts: Hosts::Many(vec!["a.internal".to_string(), "b.internal".to_string()]),
+            fallback: Some(Hosts::One("c.internal".to_string())),
+        }
+    }
+}
+
+#[test]
+fn untagged_enum_forms() {
+    let expected = "\
+# Hosts to connect to
+# Accepts one of:
+# - a single host
+# - Vec<String>
+hosts = [\"a.internal\", \"b.internal\"]
+# Accepts one of:
+# - a single host
+# - Vec<String>
+fallback = \"c.internal\"
+";
+    let toml = WithUntagged::default_toml();
+    assert_eq!(toml, expected);
+    let parsed: WithUntagged = toml::from_str(&toml).unwrap();
+    assert!(matches!(parsed.hosts, Hosts::Many(hosts) if hosts.len() == 2));
+    assert!(matches!(parsed.fallback, Some(Hosts::One(_))));
+}
+
+// --- Field annotations ---
+
+fn default_retries() -> u32 {
+    3
+}
+
+#[derive(Serialize, Deserialize, TomlComment)]
+struct WithSerdeDefaults {
+    /// Service name
+    name: String,
+    /// Retry attempts
+    #[serde(default = "default_retries")]
+    retries: u32,
+    #[serde(default)]
+    verbose: bool,
+}
+
+impl Default for WithSerdeDefaults {
+    fn default() -> Self {
+        Self {
+            name: "api".to_string(),
+            retries: default_retries(),
+            verbose: false,
+        }
+    }
+}
+
+#[test]
+fn serde_default_marked_optional() {
+    let expected = "\
+# Service name
+name = \"api\"
+# Retry attempts
+# optional — may be omitted
+retries = 3
+# optional — may be omitted
+verbose = false
+";
+    assert_eq!(WithSerdeDefaults::default_toml(), expected);
+}
+
+#[derive(Serialize, TomlComment, Default)]
+struct WithOptionalKeys {
+    /// Bind address
+    bind: String,
+    /// Upstream proxy
+    proxy: Option<String>,
+}
+
+#[test]
+fn optional_marker() {
+    let cfg = WithOptionalKeys {
+        bind: "0.0.0.0".to_string(),
+        proxy: Some("http://proxy:3128".to_string()),
+    };
+    let opts = toml_comment::RenderOptions {
+        mark_optional: true,
+        ..Default::default()
+    };
+    let expected = "\
+# Bind address
+bind = \"0.0.0.0\"
+# Upstream proxy
+# optional
+proxy = \"http://proxy:3128\"
+";
+    assert_eq!(cfg.to_commented_toml_with(&opts), expected);
+    assert!(!cfg.to_commented_toml().contains("# optional"));
+
+    let fields = cfg.fields();
+    assert!(!fields[0].optional);
+    assert!(fields[1].optional);
+    assert!(WithSerdeDefaults::default().fields()[1].optional);
+}
+
+#[derive(Serialize, TomlComment, Default)]
+struct WithTypeHints {
+    /// Listen port
+    port: u16,
+    /// Allowed users
+    users: Vec<String>,
+    ratio: f64,
+    limits: Vec<Vec<u32>>,
+    proxy: Option<String>,
+}
+
+#[test]
+fn type_hints() {
+    let cfg = WithTypeHints {
+        proxy: Some("socks5://127.0.0.1".to_string()),
+        ..Default::default()
+    };
+    let opts = toml_comment::RenderOptions {
+        type_hints: true,
+        ..Default::default()
+    };
+    let expected = "\
+# Listen port
+# type: integer
+port = 0
+# Allowed users
+# type: array of strings
+users = []
+# type: float
+ratio = 0.0
+# type: array of arrays of integers
+limits = []
+# type: string
+proxy = \"socks5://127.0.0.1\"
+";
+    assert_eq!(cfg.to_commented_toml_with(&opts), expected);
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithUnits {
+    /// Idle timeout
+    #[toml_comment(unit = "seconds")]
+    idle_timeout: u64,
+    #[toml_comment(unit = "MiB")]
+    cache_size: u32,
+}
+
+impl Default for WithUnits {
+    fn default() -> Self {
+        Self {
+            idle_timeout: 300,
+            cache_size: 64,
+        }
+    }
+}
+
+#[test]
+fn unit_annotations() {
+    let expected = "\
+# Idle timeout
+# unit: seconds
+idle_timeout = 300
+# unit: MiB
+cache_size = 64
+";
+    assert_eq!(WithUnits::default_toml(), expected);
+    let fields = WithUnits::default().fields();
+    assert_eq!(fields[0].unit, Some("seconds"));
+    assert_eq!(fields[1].unit, Some("MiB"));
+}
+
+#[derive(Serialize, TomlComment, Default)]
+struct WithExamples {
+    /// Cache connection string
+    #[toml_comment(example = "redis://localhost:6379")]
+    cache_url: String,
+    /// Admin port, off when 0
+    #[toml_comment(example = 8443)]
+    admin_port: u16,
+}
+
+#[test]
+fn example_annotations() {
+    let expected = "\
+# Cache connection string
+# e.g. \"redis://localhost:6379\"
+cache_url = \"\"
+# Admin port, off when 0
+# e.g. 8443
+admin_port = 0
+";
+    assert_eq!(WithExamples::default_toml(), expected);
+}
+
+#[derive(Serialize, TomlComment, Default)]
+struct WithSeeAlso {
+    /// Certificate chain
+    #[toml_comment(see = "https://docs.myapp.io/config#tls", see = "tls_key")]
+    tls_cert: String,
+    tls_key: String,
+}
+
+#[test]
+fn see_also_annotations() {
+    let expected = "\
+# Certificate chain
+# see: https://docs.myapp.io/config#tls
+# see: tls_key
+tls_cert = \"\"
+tls_key = \"\"
+";
+    assert_eq!(WithSeeAlso::default_toml(), expected);
+}
+
+#[derive(Serialize, TomlComment, Default)]
+struct WithRelations {
+    /// Serve over HTTPS
+    #[toml_comment(requires = "tls.cert_path", conflicts_with = "plain_http")]
+    https: bool,
+    plain_http: bool,
+}
+
+#[test]
+fn relation_annotations() {
+    let expected = "\
+# Serve over HTTPS
+# requires: tls.cert_path
+# conflicts with: plain_http
+https = false
+plain_http = false
+";
+    assert_eq!(WithRelations::default_toml(), expected);
+}
+
+#[derive(Serialize, TomlComment, Default)]
+struct CheckedRelations {
+    /// Serve over HTTPS
+    #[toml_comment(requires = "tls.cert_path", conflicts_with = "plain_http")]
+    https: bool,
+    plain_http: bool,
+    tls: CheckedTls,
+}
+
+#[derive(Serialize, TomlComment, Default)]
+struct CheckedTls {
+    cert_path: Option<String>,
+    /// Client certificate
+    #[toml_comment(requires = "cert_path")]
+    client_cert: String,
+}
+
+#[test]
+fn validate_relations() {
+    let mut cfg = CheckedRelations::default();
+    assert_eq!(cfg.validate(), Ok(()));
+
+    cfg.https = true;
+    cfg.plain_http = true;
+    cfg.tls.client_cert = "client.pem".to_string();
+    let violations = cfg.validate().unwrap_err();
+    assert_eq!(
+        violations,
+        [
+            toml_comment::Violation::Requires {
+                key: "https".to_string(),
+                requires: "tls.cert_path".to_string(),
+            },
+            toml_comment::Violation::ConflictsWith {
+                key: "https".to_string(),
+                conflicts_with: "plain_http".to_string(),
+            },
+            toml_comment::Violation::Requires {
+                key: "tls.client_cert".to_string(),
+                requires: "tls.cert_path".to_string(),
+            },
+        ]
+    );
+    assert_eq!(
+        violations[0].to_string(),
+        "`https` requires `tls.cert_path`"
+    );
+
+    cfg.plain_http = false;
+    cfg.tls.cert_path = Some("server.pem".to_string());
+    assert_eq!(cfg.validate(), Ok(()));
+}
+
+#[derive(Serialize, Deserialize, TomlComment)]
+struct WithAliases {
+    /// Listen address
+    #[serde(alias = "bind", alias = "listen_addr")]
+    listen: String,
+}
+
+impl Default for WithAliases {
+    fn default() -> Self {
+        Self {
+            listen: "0.0.0.0:80".to_string(),
+        }
+    }
+}
+
+#[test]
+fn serde_alias_annotation() {
+    let expected = "\
+# Listen address
+# also accepted: bind, listen_addr
+listen = \"0.0.0.0:80\"
+";
+    assert_eq!(WithAliases::default_toml(), expected);
+    let old: WithAliases = toml::from_str("bind = \"127.0.0.1:80\"").unwrap();
+    assert_eq!(old.listen, "127.0.0.1:80");
+}
+
+/// Upstream server
+#[derive(Serialize, Deserialize, TomlComment, Default, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct WithRenamedKeys {
+    /// Listen address
+    #[serde(rename = "addr", alias = "listen")]
+    listen_address: String,
+    /// Maximum open connections
+    max_connections: u32,
+    tls_options: RenamedTls,
+}
+
+#[derive(Serialize, Deserialize, TomlComment, Default, PartialEq, Debug)]
+struct RenamedTls {
+    /// Verify the peer certificate
+    verify_peer: bool,
+}
+
+#[test]
+fn serde_renamed_keys() {
+    let expected = "\
+# Upstream server
+# Listen address
+# also accepted: listen
+addr = \"\"
+# Maximum open connections
+max-connections = 0
+
+[tls-options]
+# Verify the peer certificate
+verify_peer = false
+";
+    let toml = WithRenamedKeys::default_toml();
+    assert_eq!(toml, expected);
+    let parsed: WithRenamedKeys = toml::from_str(&toml).unwrap();
+    assert_eq!(parsed, WithRenamedKeys::default());
+    let paths: Vec<String> = WithRenamedKeys::default()
+        .fields()
+        .into_iter()
+        .map(|f| f.path)
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "addr",
+            "max-connections",
+            "tls-options",
+            "tls-options.verify_peer"
+        ]
+    );
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct WithTuples {
+    /// Worker port range
+    #[toml_comment(elements = "first port, last port")]
+    range: (u16, u16),
+    /// Log target and level
+    target: (String, u8),
+}
+
+impl Default for WithTuples {
+    fn default() -> Self {
+        Self {
+            range: (9000, 9100),
+            target: ("stderr".to_string(), 3),
+        }
+    }
+}
+
+#[test]
+fn tuple_fields() {
+    let opts = toml_comment::RenderOptions {
+        type_hints: true,
+        ..Default::default()
+    };
+    let expected = "\
+# Worker port range
+# elements: first port, last port
+# type: array [integer, integer]
+range = [9000, 9100]
+# Log target and level
+# type: array [string, integer]
+target = [\"stderr\", 3]
+";
+    let toml = WithTuples::default().to_commented_toml_with(&opts);
+    assert_eq!(toml, expected);
+    assert_eq!(
+        toml::from_str::<WithTuples>(&toml).unwrap(),
+        WithTuples::default()
+    );
+}
+
+// --- Value formatting ---
+
+#[derive(Serialize, Deserialize, TomlComment)]
+struct WithByteSizes {
+    /// Cache size ({default})
+    #[serde(with = "toml_comment::bytesize")]
+    cache: u64,
+    #[serde(with = "toml_comment::bytesize")]
+    upload_limit: u64,
+}
+
+impl Default for WithByteSizes {
+    fn default() -> Self {
+        Self {
+            cache: 256 << 20,
+            upload_limit: 1_500_000,
+        }
+    }
+}
+
+#[test]
+fn byte_size_values() {
+    let expected = "\
+# Cache size (\"256MiB\")
+cache = \"256MiB\"
+upload_limit = \"1500kB\"
+";
+    let toml = WithByteSizes::default_toml();
+    assert_eq!(toml, expected);
+    let parsed: WithByteSizes = toml::from_str(&toml).unwrap();
+    assert_eq!(parsed.cache, 256 << 20);
+    assert_eq!(parsed.upload_limit, 1_500_000);
+    assert_eq!(
+        WithByteSizes::default().fields()[0].value,
+        Some(toml::Value::String("256MiB".to_string()))
+    );
+
+    let parsed: WithByteSizes = toml::from_str("cache = 4096\nupload_limit = \"1.5 GiB\"").unwrap();
+    assert_eq!(parsed.cache, 4096);
+    assert_eq!(parsed.upload_limit, 3 << 29);
+    assert!(toml::from_str::<WithByteSizes>("cache = \"12 parsecs\"\nupload_limit = 0").is_err());
+}
+
+#[test]
+fn byte_size_type_hints() {
+    let opts = toml_comment::RenderOptions {
+        type_hints: true,
+        ..Default::default()
+    };
+    let expected = "\
+# Cache size (\"256MiB\")
+# type: string
+cache = \"256MiB\"
+# type: string
+upload_limit = \"1500kB\"
+";
+    assert_eq!(
+        WithByteSizes::default().to_commented_toml_with(&opts),
+        expected
+    );
+}
+
+/// Stand-in for the `humantime-serde` crate: same module shape, seconds only.
+mod humantime_serde {
+    use std::time::Duration;
+
+    pub fn serialize<S: serde::Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        let secs = d.as_secs();
+        let text = match (secs / 60, secs % 60) {
+            (0, s) => format!("{s}s"),
+            (m, 0) => format!("{m}m"),
+            (m, s) => format!("{m}m {s}s"),
+        };
+        s.serialize_str(&text)
+    }
+
+    pub mod option {
+        use std::time::Duration;
+
+        pub fn serialize<S: serde::Serializer>(
+            d: &Option<Duration>,
+            s: S,
+        ) -> Result<S::Ok, S::Error> {
+            match d {
+                Some(d) => super::serialize(d, s),
+                None => s.serialize_none(),
+            }
+        }
+    }
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithDurations {
+    /// Request timeout
+    #[serde(with = "humantime_serde")]
+    timeout: std::time::Duration,
+    #[serde(with = "humantime_serde::option")]
+    grace: Option<std::time::Duration>,
+}
+
+impl Default for WithDurations {
+    fn default() -> Self {
+        Self {
+            timeout: std::time::Duration::from_secs(150),
+            grace: Some(std::time::Duration::from_secs(30)),
+        }
+    }
+}
+
+#[test]
+fn humantime_values() {
+    let expected = "\
+# Request timeout
+timeout = \"2m 30s\"
+grace = \"30s\"
+";
+    assert_eq!(WithDurations::default_toml(), expected);
+    assert_eq!(
+        WithDurations::default().fields()[0].kind,
+        toml_comment::FieldKind::Value
+    );
+}
+
+/// Stand-in for `ordered_float::OrderedFloat`, which serializes as its float.
+#[derive(Serialize, Default, PartialEq, PartialOrd)]
+#[serde(transparent)]
+struct OrderedFloat<T>(T);
+
+#[derive(Serialize, TomlComment, Default)]
+struct WithOrderedFloats {
+    /// Sampling ratio
+    ratio: OrderedFloat<f64>,
+    weights: Vec<OrderedFloat<f64>>,
+}
+
+#[test]
+fn ordered_float_values() {
+    let cfg = WithOrderedFloats {
+        ratio: OrderedFloat(0.25),
+        weights: vec![OrderedFloat(1.0), OrderedFloat(0.5)],
+    };
+    let opts = toml_comment::RenderOptions {
+        type_hints: true,
+        ..Default::default()
+    };
+    let expected = "\
+# Sampling ratio
+# type: float
+ratio = 0.25
+# type: array of floats
+weights = [1.0, 0.5]
+";
+    assert_eq!(cfg.to_commented_toml_with(&opts), expected);
+    assert_eq!(cfg.fields()[0].kind, toml_comment::FieldKind::Value);
+}
+
+#[derive(Serialize, Deserialize, TomlComment)]
+struct WithIntFormats {
+    /// Feature mask
+    #[toml_comment(format = "hex")]
+    mask: u32,
+    #[toml_comment(format = "octal")]
+    mode: u32,
+    #[toml_comment(format = "binary", separators)]
+    flags: u8,
+    #[toml_comment(separators)]
+    max_rows: i64,
+    #[toml_comment(format = "hex")]
+    offsets: Vec<i64>,
+}
+
+impl Default for WithIntFormats {
+    fn default() -> Self {
+        Self {
+            mask: 0xff00,
+            mode: 0o644,
+            flags: 0b1010_0101,
+            max_rows: 10_000_000,
+            offsets: vec![16, -1],
+        }
+    }
+}
+
+#[test]
+fn integer_formats() {
+    let expected = "\
+# Feature mask
+mask = 0xff00
+mode = 0o644
+flags = 0b1010_0101
+max_rows = 10_000_000
+offsets = [0x10, -1]
+";
+    let toml = WithIntFormats::default_toml();
+    assert_eq!(toml, expected);
+    let parsed: WithIntFormats = toml::from_str(&toml).unwrap();
+    assert_eq!(parsed.mask, 0xff00);
+    assert_eq!(parsed.mode, 0o644);
+    assert_eq!(parsed.flags, 0b1010_0101);
+    assert_eq!(parsed.max_rows, 10_000_000);
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithPrecision {
+    /// Share of traffic to sample
+    #[toml_comment(precision = 2)]
+    ratio: f64,
+    #[toml_comment(precision = 2)]
+    split: f64,
+    #[toml_comment(precision = 0)]
+    weights: Vec<f64>,
+}
+
+impl Default for WithPrecision {
+    fn default() -> Self {
+        Self {
+            ratio: 0.333333333,
+            split: 0.75,
+            weights: vec![2.4, 7.0],
+        }
+    }
+}
+
+#[test]
+fn float_precision() {
+    let expected = "\
+# Share of traffic to sample
+ratio = 0.33
+split = 0.75
+weights = [2.0, 7.0]
+";
+    let toml = WithPrecision::default_toml();
+    assert_eq!(toml, expected);
+    assert!(toml.parse::<toml::Table>().is_ok());
+}
+
+#[derive(Serialize, Deserialize, TomlComment)]
+struct WithScientific {
+    /// Bytes per second
+    #[toml_comment(scientific)]
+    bandwidth: f64,
+    #[toml_comment(scientific)]
+    epsilon: f64,
+    #[toml_comment(scientific)]
+    ratio: f64,
+    learning_rate: f64,
+}
+
+impl Default for WithScientific {
+    fn default() -> Self {
+        Self {
+            bandwidth: 1.5e9,
+            epsilon: 2e-5,
+            ratio: 0.5,
+            learning_rate: 3e-7,
+        }
+    }
+}
+
+#[test]
+fn scientific_floats() {
+    let expected = "\
+# Bytes per second
+bandwidth = 1.5e9
+epsilon = 2e-5
+ratio = 0.5
+learning_rate = 0.0000003
+";
+    let toml = WithScientific::default_toml();
+    assert_eq!(toml, expected);
+    let parsed: WithScientific = toml::from_str(&toml).unwrap();
+    assert_eq!(parsed.bandwidth, 1.5e9);
+    assert_eq!(parsed.epsilon, 2e-5);
+
+    let opts = toml_comment::RenderOptions {
+        scientific_floats: true,
+        ..Default::default()
+    };
+    let toml = WithScientific::default().to_commented_toml_with(&opts);
+    assert!(toml.ends_with("learning_rate = 3e-7\n"));
+    assert!(toml.parse::<toml::Table>().is_ok());
+}
+
+// --- Layout ---
+
+#[test]
+fn indented_sections() {
+    let opts = toml_comment::RenderOptions {
+        indent: 2,
+        ..Default::default()
+    };
+    let expected = "\
+# Max upload size in MB
+max_size = 0
+# Storage backend
+[storage]
+  # Where uploads are stored
+  # Local disk
+  type = \"local\"
+  # Directory to write to
+  path = \"/var/lib/uploads\"
+";
+    let toml = WithStorage::default().to_commented_toml_with(&opts);
+    assert_eq!(toml, expected);
+    assert!(toml.parse::<toml::Table>().is_ok());
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct IndentedValues {
+    /// Values
+    values: LongValues,
+}
+
+#[test]
+fn indented_multiline_values() {
+    let cfg = IndentedValues {
+        values: LongValues {
+            banner: "[x] marks the spot  # not a comment".to_string(),
+            groups: vec![vec![1, 2], vec![3]],
+            footer: "end".to_string(),
+        },
+    };
+    let opts = toml_comment::RenderOptions {
+        indent: 2,
+        line_width: Some(20),
+        ..Default::default()
+    };
+    let expected = "\
+# Values
+[values]
+  # Shown on the login page
+  banner = \"\"\"\\
+    [x] marks the \\
+    spot  # not a \\
+    comment\"\"\"
+  # Port groups
+  groups = [
+      [1, 2],
+      [3],
+  ]
+  # Two lines
+  footer = \"end\"
+";
+    let toml = cfg.to_commented_toml_with(&opts);
+    assert_eq!(toml, expected);
+    assert_eq!(toml::from_str::<IndentedValues>(&toml).unwrap(), cfg);
+}
+
+#[test]
+fn comment_prefixes() {
+    let opts = toml_comment::RenderOptions {
+        comment_prefix: Some("#  "),
+        type_comment_prefix: Some("## "),
+        ..Default::default()
+    };
+    let expected = "\
+#  Max upload size in MB
+max_size = 0
+#  Storage backend
+[storage]
+## Where uploads are stored
+#  Local disk
+type = \"local\"
+#  Directory to write to
+path = \"/var/lib/uploads\"
+";
+    assert_eq!(
+        WithStorage::default().to_commented_toml_with(&opts),
+        expected
+    );
+}
+
+#[test]
+fn section_banners() {
+    let opts = toml_comment::RenderOptions {
+        banner_width: Some(24),
+        ..Default::default()
+    };
+    let expected = "\
+# Max upload size in MB
+max_size = 0
+# ======================
+# == Storage ===========
+# ======================
+# Storage backend
+[storage]
+# Where uploads are stored
+# Local disk
+type = \"local\"
+# Directory to write to
+path = \"/var/lib/uploads\"
+";
+    let toml = WithStorage::default().to_commented_toml_with(&opts);
+    assert_eq!(toml, expected);
+
+    let toml = sample_menu().to_commented_toml_with(&opts);
+    assert_eq!(toml.matches("# == Items ").count(), 1);
+}
+
+#[test]
+fn blank_line_policy() {
+    let opts = toml_comment::RenderOptions {
+        section_gap: Some(2),
+        ..Default::default()
+    };
+    let toml = sample_menu().to_commented_toml_with(&opts);
+    assert!(toml.contains("label = \"Open\"\n\n\n[[items]]\n"));
+
+    let opts = toml_comment::RenderOptions {
+        section_gap: Some(0),
+        ..Default::default()
+    };
+    let toml = sample_menu().to_commented_toml_with(&opts);
+    assert!(!toml.contains("\n\n"));
+    assert!(toml.parse::<toml::Table>().is_ok());
+
+    let opts = toml_comment::RenderOptions {
+        blank_after_docs: true,
+        ..Default::default()
+    };
+    let toml = AppConfig::default().to_commented_toml_with(&opts);
+    assert!(toml.starts_with("# Application settings\n\n# "));
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithLongArray {
+    /// Hosts allowed to connect
+    allowed_hosts: Vec<String>,
+    /// Per-host limits
+    limits: BTreeMap<String, BTreeMap<String, u32>>,
+}
+
+impl Default for WithLongArray {
+    fn default() -> Self {
+        Self {
+            allowed_hosts: [
+                "alpha.example.com",
+                "beta.example.com",
+                "gamma.example.com",
+                "delta.example.com",
+            ]
+            .map(String::from)
+            .to_vec(),
+            limits: BTreeMap::from([("alpha".to_string(), BTreeMap::new())]),
+        }
+    }
+}
+
+#[test]
+fn taplo_profile() {
+    let expected = "\
+# Hosts allowed to connect
+allowed_hosts = [
+  \"alpha.example.com\",
+  \"beta.example.com\",
+  \"gamma.example.com\",
+  \"delta.example.com\",
+]
+# Per-host limits
+alpha = {}
+";
+    let toml =
+        WithLongArray::default().to_commented_toml_with(&toml_comment::RenderOptions::taplo());
+    assert_eq!(toml, expected);
+    assert!(
+        WithLongArray::default_toml().contains("allowed_hosts = [\n    \"alpha.example.com\",\n")
+    );
+}
+
+#[derive(Serialize, TomlComment)]
+struct PrettyValues {
+    /// Listening ports
+    ports: Vec<u16>,
+    /// Just one
+    tags: Vec<String>,
+    /// Message of the day
+    motd: String,
+    /// Permission bits
+    #[toml_comment(format = "octal")]
+    mode: u32,
+}
+
+impl Default for PrettyValues {
+    fn default() -> Self {
+        Self {
+            ports: vec![80, 443],
+            tags: vec!["web".to_string()],
+            motd: "Welcome!\nBe nice.".to_string(),
+            mode: 0o644,
+        }
+    }
+}
+
+#[test]
+fn toml_pretty_values() {
+    let opts = toml_comment::RenderOptions {
+        toml_pretty: true,
+        ..Default::default()
+    };
+    let toml = PrettyValues::default().to_commented_toml_with(&opts);
+    let values: String = toml
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    let pretty = toml::to_string_pretty(&PrettyValues::default()).unwrap();
+    assert_eq!(values, pretty.replace("mode = 420", "mode = 0o644"));
+    assert!(toml.contains("# Listening ports\nports = [\n    80,\n    443,\n]\n"));
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct PrettyNotice {
+    /// Notice
+    notice: PrettyText,
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct PrettyText {
+    /// Message body
+    body: String,
+    /// Lines of the footer
+    footer: Vec<String>,
+}
+
+#[test]
+fn toml_pretty_indented() {
+    let cfg = PrettyNotice {
+        notice: PrettyText {
+            body: "line1\n[x]\n# y".to_string(),
+            footer: vec!["a\nb".to_string(), "c".to_string()],
+        },
+    };
+    let opts = toml_comment::RenderOptions {
+        toml_pretty: true,
+        indent: 2,
+        ..Default::default()
+    };
+    let expected = "\
+# Notice
+[notice]
+  # Message body
+  body = \"\"\"
+line1
+[x]
+# y\"\"\"
+  # Lines of the footer
+  footer = [
+      \"\"\"
+a
+b\"\"\",
+      \"c\",
+  ]
+";
+    let toml = cfg.to_commented_toml_with(&opts);
+    assert_eq!(toml, expected);
+    assert_eq!(toml::from_str::<PrettyNotice>(&toml).unwrap(), cfg);
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct LongValues {
+    /// Shown on the login page
+    banner: String,
+    /// Port groups
+    groups: Vec<Vec<u16>>,
+    /// Two lines
+    footer: String,
+}
+
+impl Default for LongValues {
+    fn default() -> Self {
+        Self {
+            banner: "Authorized use only.  All activity may be monitored and reported \
+                     to \"security\" staff."
+                .to_string(),
+            groups: vec![vec![8000, 8001, 8002, 8003, 8004], vec![9000]],
+            footer: "line one\nline two".to_string(),
+        }
+    }
+}
+
+#[test]
+fn long_values_wrap() {
+    let opts = toml_comment::RenderOptions {
+        line_width: Some(40),
+        ..Default::default()
+    };
+    let toml = LongValues::default().to_commented_toml_with(&opts);
+    let expected = "\
+# Shown on the login page
+banner = \"\"\"\\
+    Authorized use only.  All activity \\
+    may be monitored and reported to \\
+    \\\"security\\\" staff.\"\"\"
+# Port groups
+groups = [
+    [8000, 8001, 8002, 8003, 8004],
+    [9000],
+]
+# Two lines
+footer = \"line one\\nline two\"
+";
+    assert_eq!(toml, expected);
+    let parsed: LongValues = toml::from_str(&toml).unwrap();
+    assert_eq!(parsed, LongValues::default());
+
+    let toml = LongValues::default_toml();
+    assert!(
+        toml.contains(
+            "banner = \"\"\"\\\n    Authorized use only.  All activity may be monitored "
+        )
+    );
+    assert_eq!(
+        toml::from_str::<LongValues>(&toml).unwrap(),
+        LongValues::default()
+    );
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct WideTables {
+    /// Backends by name
+    #[toml_comment(inline)]
+    backends: BTreeMap<String, WideBackend>,
+    /// Retries per request
+    retries: u32,
+    /// HTTP server
+    server: SplitServer,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct WideBackend {
+    port: u16,
+    host: String,
+}
+
+#[test]
+fn long_inline_tables_as_dotted_keys() {
+    let cfg = WideTables {
+        backends: BTreeMap::from([
+            (
+                "primary".to_string(),
+                WideBackend {
+                    port: 8080,
+                    host: "10.0.0.1".to_string(),
+                },
+            ),
+            (
+                "replica".to_string(),
+                WideBackend {
+                    port: 8081,
+                    host: "backend-replica.internal.example.com".to_string(),
+                },
+            ),
+        ]),
+        retries: 3,
+        server: SplitServer { port: 80 },
+    };
+    let opts = toml_comment::RenderOptions {
+        line_width: Some(60),
+        ..Default::default()
+    };
+    let toml = cfg.to_commented_toml_with(&opts);
+    let expected = "\
+# Backends by name
+backends.primary = { host = \"10.0.0.1\", port = 8080 }
+backends.replica.host = \"backend-replica.internal.example.com\"
+backends.replica.port = 8081
+# Retries per request
+retries = 3
+# HTTP server
+[server]
+# Port to listen on
+port = 80
+";
+    assert_eq!(toml, expected);
+    let parsed: WideTables = toml::from_str(&toml).unwrap();
+    assert_eq!(parsed, cfg);
+}
+
+#[derive(Serialize, Deserialize, TomlComment, PartialEq, Debug)]
+struct WithMatrix {
+    /// Row-major transform
+    #[toml_comment(precision = 3)]
+    transform: Vec<Vec<f64>>,
+}
+
+impl Default for WithMatrix {
+    fn default() -> Self {
+        Self {
+            transform: vec![
+                vec![1.0, 0.0, 0.0, 12.5],
+                vec![0.0, 0.642787, -0.642787, 0.0],
+                vec![0.0, 0.642787, 0.642787, 0.0],
+            ],
+        }
+    }
+}
+
+#[test]
+fn nested_arrays() {
+    let expected = "\
+# Row-major transform
+transform = [
+    [1.0, 0.0, 0.0, 12.5],
+    [0.0, 0.643, -0.643, 0.0],
+    [0.0, 0.643, 0.643, 0.0],
+]
+";
+    let toml = WithMatrix::default_toml();
+    assert_eq!(toml, expected);
+    let parsed: WithMatrix = toml::from_str(&toml).unwrap();
+    assert_eq!(parsed.transform[1][1], 0.643);
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithSets {
+    /// Admin accounts ({default})
+    admins: HashSet<String>,
+    /// Blocked ports
+    blocked: BTreeSet<u16>,
+    trusted: Option<HashSet<i64>>,
+}
+
+impl Default for WithSets {
+    fn default() -> Self {
+        Self {
+            admins: ["root", "alice", "bob"].map(String::from).into(),
+            blocked: [445, 23, 135].into(),
+            trusted: Some([100, -3, 20].into()),
+        }
+    }
+}
+
+#[test]
+fn sets_as_arrays() {
+    let opts = toml_comment::RenderOptions {
+        type_hints: true,
+        ..Default::default()
+    };
+    let expected = "\
+# Admin accounts ([\"alice\", \"bob\", \"root\"])
+# type: array of strings
+admins = [\"alice\", \"bob\", \"root\"]
+# Blocked ports
+# type: array of integers
+blocked = [23, 135, 445]
+# type: array of integers
+trusted = [-3, 20, 100]
+";
+    assert_eq!(WithSets::default().to_commented_toml_with(&opts), expected);
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithQueues {
+    /// Recent searches
+    history: VecDeque<String>,
+    /// Job priorities
+    priorities: BinaryHeap<u8>,
+    fallback: Option<VecDeque<String>>,
+    /// Servers tried in turn
+    rotation: VecDeque<Upstream>,
+}
+
+impl Default for WithQueues {
+    fn default() -> Self {
+        Self {
+            history: ["b", "a"].map(String::from).into(),
+            priorities: [3, 9, 1].into(),
+            fallback: None,
+            rotation: [Upstream::default()].into(),
+        }
+    }
+}
+
+#[test]
+fn std_sequences() {
+    let expected = "\
+# Recent searches
+history = [\"b\", \"a\"]
+# Job priorities
+priorities = [1, 3, 9]
+# fallback = []
+# Servers tried in turn
+[[rotation]]
+# One backend server
+# Backend address
+addr = \"127.0.0.1:80\"
+# Relative weight
+weight = 1
+";
+    assert_eq!(WithQueues::default_toml(), expected);
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithInlineVecs {
+    /// Retry delays in ms
+    delays: smallvec::SmallVec<[u32; 4]>,
+    /// Fixed capacity tags
+    tags: arrayvec::ArrayVec<String, 2>,
+    /// Primary servers
+    primary: smallvec::SmallVec<[Upstream; 2]>,
+}
+
+impl Default for WithInlineVecs {
+    fn default() -> Self {
+        Self {
+            delays: smallvec::smallvec![100, 250],
+            tags: ["edge".to_string(), "eu".to_string()].into(),
+            primary: smallvec::smallvec![Upstream::default()],
+        }
+    }
+}
+
+#[test]
+fn small_vectors() {
+    let opts = toml_comment::RenderOptions {
+        type_hints: true,
+        ..Default::default()
+    };
+    let expected = "\
+# Retry delays in ms
+# type: array of integers
+delays = [100, 250]
+# Fixed capacity tags
+# type: array of strings
+tags = [\"edge\", \"eu\"]
+# Primary servers
+[[primary]]
+# One backend server
+# Backend address
+# type: string
+addr = \"127.0.0.1:80\"
+# Relative weight
+# type: integer
+weight = 1
+";
+    assert_eq!(
+        WithInlineVecs::default().to_commented_toml_with(&opts),
+        expected
+    );
+}
+
+#[derive(Serialize, TomlComment)]
+struct WithFixedArrays {
+    /// Accent color as RGB
+    rgb: [u8; 3],
+    /// Port range, inclusive
+    ports: Option<[u16; 2]>,
+    /// Exactly two replicas
+    replicas: [Upstream; 2],
+}
+
+impl Default for WithFixedArrays {
+    fn default() -> Self {
+        Self {
+            rgb: [255, 0, 0],
+            ports: None,
+            replicas: Default::default(),
+        }
+    }
+}
+
+#[test]
+fn fixed_size_arrays() {
+    let opts = toml_comment::RenderOptions {
+        type_hints: true,
+        ..Default::default()
+    };
+    let toml = WithFixedArrays::default().to_commented_toml_with(&opts);
+    assert!(toml.starts_with(
+        "\
+# Accent color as RGB
+# type: array of integers
+rgb = [255, 0, 0]
+# Port range, inclusive
+# type: array of integers
+# ports = []
+# Exactly two replicas
+[[replicas]]
+"
+    ));
+    assert_eq!(toml.matches("[[replicas]]").count(), 2);
+}
+
+#[derive(Serialize, TomlComment)]
+#[toml_comment(style(type_hints, line_width = 30, comment_prefix = "## "))]
+struct StyledConfig {
+    /// Mirrors to try
+    mirrors: Vec<String>,
+}
+
+impl Default for StyledConfig {
+    fn default() -> Self {
+        Self {
+            mirrors: vec![
+                "https://a.example".to_string(),
+                "https://b.example".to_string(),
+            ],
+        }
+    }
+}
+
+#[test]
+fn struct_style() {
+    let expected = "\
+## Mirrors to try
+## type: array of strings
+mirrors = [
+    \"https://a.example\",
+    \"https://b.example\",
+]
+";
+    assert_eq!(StyledConfig::default_toml(), expected);
+    assert_eq!(StyledConfig::render_options().line_width, Some(30));
+    let plain = StyledConfig::default().to_commented_toml_with(&Default::default());
+    assert!(plain.starts_with("# Mirrors to try\nmirrors = [\""));
+}
+
+#[derive(Serialize, TomlComment, Default)]
+#[toml_comment(style(preset = "compact"))]
+struct CompactSections {
+    logging: LoggingConfig,
+    database: DatabaseConfig,
+}
+
+#[test]
+fn style_presets() {
+    let expected = "\
+[logging]
+# Log level
+level = \"info\"
+[database]
+# Connection URL
+url = \"sqlite://data.db\"
+";
+    assert_eq!(CompactSections::default_toml(), expected);
+
+    let expected = "\
+# Always present
+# type: string
+# default: \"hello\"
+name = \"hello\"
+# Sometimes present
+# optional
+# type: string
+# extra = <string>
+";
+    let opts = toml_comment::RenderOptions::verbose();
+    assert_eq!(
+        WithOption::default().to_commented_toml_with(&opts),
+        expected
+    );
+}
+
+#[derive(Serialize, TomlComment)]
+struct Annotated {
+    /// Port to listen on
+    #[toml_comment(env = "APP_PORT", min = 1, max = 65535)]
+    port: u16,
+    /// Worker threads
+    #[toml_comment(min = 1)]
+    workers: u32,
+}
+
+impl Default for Annotated {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            workers: 4,
+        }
+    }
+}
+
+#[test]
+fn annotate_from_metadata() {
+    let cfg = Annotated {
+        port: 9000,
+        ..Default::default()
+    };
+    let opts = toml_comment::RenderOptions {
+        annotate: true,
+        ..Default::default()
+    };
+    let expected = "\
+# Port to listen on
+# default: 8080
+# env: APP_PORT
+# range: 1 to 65535
+port = 9000
+# Worker threads
+# default: 4
+# minimum: 1
+workers = 4
+";
+    assert_eq!(cfg.to_commented_toml_with(&opts), expected);
+}
+
+// --- Managed blocks ---
+
+#[test]
+fn managed_block() {
+    let toml = MultiSection::default().to_managed_toml("myapp");
+    let body = MultiSection::default_toml();
+    let expected = format!(
+        "# BEGIN managed by myapp \
+         (sha256:e93b09e857f0019e44498fd65e53f6935878980300d7c921e324030eab87a9fc)\n\
+         {body}# END managed\n"
+    );
+    assert_eq!(toml, expected);
+    assert_eq!(
+        toml_comment::check_managed(&toml),
+        toml_comment::ManagedBlock::Intact
+    );
+
+    let file = format!("# my overrides\nextra = 1\n\n{toml}\n[local]\nx = 2\n");
+    assert_eq!(
+        toml_comment::check_managed(&file),
+        toml_comment::ManagedBlock::Intact
+    );
+
+    let edited = toml.replace("level = \"info\"", "level = \"debug\"");
+    assert_eq!(
+        toml_comment::check_managed(&edited),
+        toml_comment::ManagedBlock::Edited
+    );
+
+    let truncated = toml.replace("# END managed\n", "");
+    assert_eq!(
+        toml_comment::check_managed(&truncated),
+        toml_comment::ManagedBlock::Missing
+    );
+    assert_eq!(
+        toml_comment::check_managed(&body),
+        toml_comment::ManagedBlock::Missing
+    );
+}
+
+#[test]
+fn regenerate_managed_block() {
+    let stale = MultiSection {
+        logging: LoggingConfig {
+            level: "trace".to_string(),
+        },
+        ..Default::default()
+    }
+    .to_managed_toml("myapp");
+    let file = format!("# my overrides\nextra = 1\n\n{stale}\n[local]\nx = 2\n");
+    let fresh = MultiSection::regenerate_managed(&file);
+    assert_eq!(
+        fresh,
+        format!(
+            "# my overrides\nextra = 1\n\n{}\n[local]\nx = 2\n",
+            MultiSection::default().to_managed_toml("myapp")
+        )
+    );
+    assert_eq!(
+        toml_comment::check_managed(&fresh),
+        toml_comment::ManagedBlock::Intact
+    );
+
+    let plain = "port = 1\n";
+    assert_eq!(MultiSection::regenerate_managed(plain), plain);
+}
+
+// --- Config diff ---
+
+#[derive(Serialize, TomlComment)]
+struct ServerV1 {
+    /// Port to listen on
+    port: u16,
+    /// Bind address
+    host: String,
+    /// Worker threads
+    workers: u32,
+}
+
+impl Default for ServerV1 {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            host: "127.0.0.1".to_string(),
+            workers: 4,
+        }
+    }
+}
+
+#[derive(Serialize, TomlComment)]
+struct ServerV2 {
+    /// Port to listen on
+    port: u16,
+    /// Bind address
+    bind: String,
+    /// Request timeout in seconds
+    timeout: u64,
+}
+
+impl Default for ServerV2 {
+    fn default() -> Self {
+        Self {
+            port: 9090,
+            bind: "127.0.0.1".to_string(),
+            timeout: 30,
+        }
+    }
+}
+
+#[test]
+fn config_diff() {
+    let report =
+        toml_comment::diff_fields(&ServerV1::default().fields(), &ServerV2::default().fields());
+    let paths = |fields: &[toml_comment::FieldInfo]| -> Vec<String> {
+        fields.iter().map(|f| f.path.clone()).collect()
+    };
+    assert_eq!(paths(&report.added), ["timeout"]);
+    assert_eq!(paths(&report.removed), ["workers"]);
+    assert_eq!(report.removed[0].docs, [" Worker threads"]);
+    assert_eq!(report.renamed.len(), 1);
+    assert_eq!(report.renamed[0].old.path, "host");
+    assert_eq!(report.renamed[0].new.path, "bind");
+    assert_eq!(report.default_changed.len(), 1);
+    assert_eq!(report.default_changed[0].old.value, Some(8080.into()));
+    assert_eq!(report.default_changed[0].new.value, Some(9090.into()));
+
+    let same = ServerV1::default().fields();
+    assert!(toml_comment::diff_fields(&same, &same).is_empty());
+}
+
+// --- Visitor ---
+
+#[derive(Default)]
+struct EventLog(Vec<String>);
+
+impl toml_comment::RenderVisitor for EventLog {
+    fn on_comment(&mut self, line: &str) {
+        self.0.push(format!("# {line}"));
+    }
+
+    fn on_key_value(&mut self, key: &str, field: &toml_comment::FieldInfo) {
+        let value = field.value.as_ref().map(toml_comment::fmt_value);
+        self.0
+            .push(format!("{key} = {}", value.unwrap_or_default()));
+    }
+
+    fn on_section_start(&mut self, key: &str, _field: &toml_comment::FieldInfo) {
+        self.0.push(format!("start {key}"));
+    }
+
+    fn on_section_end(&mut self, key: &str) {
+        self.0.push(format!("end {key}"));
+    }
+
+    fn on_element_start(&mut self, index: usize) {
+        self.0.push(format!("element {index}"));
+    }
+
+    fn on_element_end(&mut self, index: usize) {
+        self.0.push(format!("end element {index}"));
+    }
+}
+
+#[test]
+fn visitor_events() {
+    let mut log = EventLog::default();
+    Balancer::default().visit(&mut log);
+    assert_eq!(
+        log.0,
+        [
+            "# Balancing strategy",
+            "strategy = \"round-robin\"",
+            "# Servers to balance across",
+            "start upstream",
+            "element 0",
+            "# Backend address",
+            "addr = \"127.0.0.1:80\"",
+            "# Relative weight",
+            "weight = 1",
+            "end element 0",
+            "element 1",
+            "# Backend address",
+            "addr = \"127.0.0.1:81\"",
+            "# Relative weight",
+            "weight = 2",
+            "end element 1",
+            "end upstream",
+            "# Standby servers",
+            "start standby",
+            "element 0",
+            "# Backend address",
+            "addr = \"127.0.0.1:80\"",
+            "# Relative weight",
+            "weight = 1",
+            "end element 0",
+            "end standby",
+            "# No entries, nothing rendered",
+            "start spare",
+            "end spare",
+        ]
+    );
+
+    let mut log = EventLog::default();
+    RootConfig::default().visit(&mut log);
+    assert_eq!(log.0.first().map(String::as_str), Some("start server"));
+    assert_eq!(log.0.last().map(String::as_str), Some("end server"));
+}
+
+#[test]
+fn commented_yaml() {
+    let expected = "\
+# Balancing strategy
+strategy: \"round-robin\"
+# Servers to balance across
+upstream:
+  -
+    # Backend address
+    addr: \"127.0.0.1:80\"
+    # Relative weight
+    weight: 1
+  -
+    # Backend address
+    addr: \"127.0.0.1:81\"
+    # Relative weight
+    weight: 2
+# Standby servers
+standby:
+  -
+    # Backend address
+    addr: \"127.0.0.1:80\"
+    # Relative weight
+    weight: 1
+# No entries, nothing rendered
+spare: []
+";
+    assert_eq!(Balancer::default().to_commented_yaml(), expected);
+
+    let yaml = EnvRoot::default().to_commented_yaml();
+    assert!(yaml.contains("# Optional API token\ntoken: null\n"));
+    assert!(yaml.contains("server:\n  # Port to listen on\n  port: 8080\n"));
+}
+
+#[test]
+fn commented_jsonc() {
+    let expected = "\
+{
+  // Balancing strategy
+  \"strategy\": \"round-robin\",
+  // Servers to balance across
+  \"upstream\": [
+    {
+      // Backend address
+      \"addr\": \"127.0.0.1:80\",
+      // Relative weight
+      \"weight\": 1
+    },
+    {
+      // Backend address
+      \"addr\": \"127.0.0.1:81\",
+      // Relative weight
+      \"weight\": 2
+    }
+  ],
+  // Standby servers
+  \"standby\": [
+    {
+      // Backend address
+      \"addr\": \"127.0.0.1:80\",
+      // Relative weight
+      \"weight\": 1
+    }
+  ],
+  // No entries, nothing rendered
+  \"spare\": []
+}
+";
+    assert_eq!(Balancer::default().to_commented_jsonc(), expected);
+
+    let jsonc = EnvRoot::default().to_commented_jsonc();
+    assert!(jsonc.contains("  // Optional API token\n  \"token\": null,\n"));
+}
+
+#[test]
+fn other_formats_keep_notes() {
+    let yaml = WithUnits::default().to_commented_yaml();
+    assert_eq!(
+        yaml,
+        "# Idle timeout\n# unit: seconds\nidle_timeout: 300\n# unit: MiB\ncache_size: 64\n"
+    );
+    let yaml = WithExamples::default().to_commented_yaml();
+    assert!(yaml.starts_with("# Cache connection string\n# e.g. \"redis://localhost:6379\"\n"));
+    let jsonc = WithSeeAlso::default().to_commented_jsonc();
+    assert!(jsonc.contains(
+        "  // Certificate chain\n  // see: https://docs.myapp.io/config#tls\n  // see: tls_key\n"
+    ));
+}